@@ -1,107 +1,914 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, FixedOffset};
 use rayon::prelude::*;
 
 use promkit::{
-    crossterm::{event::Event, style::ContentStyle},
+    crossterm::{
+        event::Event,
+        style::{Attribute, ContentStyle},
+    },
     grapheme::StyledGraphemes,
     listbox,
     pane::Pane,
     snapshot::Snapshot,
     switch::ActiveKeySwitcher,
-    text_editor, PaneFactory, Prompt, PromptSignal,
+    text, text_editor, PaneFactory, Prompt, PromptSignal,
 };
 
-use crate::sig;
+use crate::{
+    config::Theme,
+    dedupe::Dedupe,
+    editing::Undo,
+    extract::Extractor,
+    gutter::{self, Gutter},
+    help::{Help, Settings},
+    history, json,
+    keybinding::{ModeKeybindings, Preset},
+    level::Levels,
+    logfmt, pod, sig,
+    stats::Stats,
+    terminal::Height,
+    timestamp,
+    vi::{self, Vi},
+};
 
+mod export;
 mod keymap;
+mod patterns;
+mod pods;
+mod table;
 
 struct Archived {
     keymap: ActiveKeySwitcher<keymap::Keymap>,
+    keybindings: ModeKeybindings<keymap::Action, keymap::PromptAction>,
     text_editor_snapshot: Snapshot<text_editor::State>,
     lines: Snapshot<listbox::State>,
+    /// Lines in the original queue with where and when they arrived,
+    /// whose gutters are rendered as they are drawn to keep relative times current.
+    queue: Vec<gutter::Line>,
+    gutter: Gutter,
+    /// Positions in the original queue of the items currently listed in `lines`.
+    indices: Vec<usize>,
+    /// Positions in the original queue of the lines marked for printing.
+    marked: BTreeSet<usize>,
+    /// Lines to print on exit, set just before quitting.
+    chosen: Option<Vec<String>>,
+    /// Surrounding lines of the selected item, shown instead of `lines` while expanded.
+    context: Option<listbox::State>,
+    /// Position in the original queue of the first line of `context`.
+    context_start: usize,
+    context_lines: usize,
+    /// Whether the selected line is shown pretty-printed below the lines, if it is JSON.
+    preview: bool,
+    /// Prompt for the file to export lines to, shown while exporting.
+    export: Option<export::Export>,
+    /// Fields of the records shown as columns in place of the lines.
+    table: Option<table::Table>,
+    /// Prompt for the fields to show as columns, shown while picking them.
+    columns: Option<text_editor::State>,
+    /// Timestamps of the lines in the original queue, each line without one
+    /// taking that of the line before it (e.g. of a stack trace).
+    times: Vec<Option<DateTime<FixedOffset>>>,
+    /// Filter of the lines by their timestamps, on top of the query.
+    time_range: Option<timestamp::Range>,
+    /// Prompt for the time filter, shown while entering it.
+    time_prompt: Option<text_editor::State>,
+    /// Pods of the lines in the original queue, for those with a pod prefix.
+    pods: Vec<Option<String>>,
+    /// Pods whose lines are filtered out.
+    hidden_pods: BTreeSet<String>,
+    /// Overlay for picking the pods to show, in place of `lines` while open.
+    pod_picker: Option<pods::Picker>,
+    /// Overlay listing the patterns of the lines, in place of `lines` while open.
+    patterns: Option<patterns::Patterns>,
+    /// Counts of the matches of the query over time, shown below the lines while set.
+    stats: Option<Stats>,
+    /// Whether only what the query captures is shown and printed of the lines,
+    /// if it has capture groups.
+    only_captures: bool,
+    /// Search through the query history, while it is in progress.
+    search: Option<history::Search>,
+    vi: Vi,
+    /// Text deleted last, for yanking.
+    killed: String,
+    /// Changes to the query, for undoing.
+    undo: Undo,
+    /// Overlay listing the key bindings, shown in place of `lines` while open.
+    help: Option<Help>,
+    /// Message about the last action, cleared on the next key.
+    notice: Option<String>,
     highlight_style: ContentStyle,
+    fields: logfmt::Fields,
+    levels: Levels,
     case_insensitive: bool,
+    /// How the repeats of lines were collapsed as they arrived.
+    dedupe: Option<Dedupe>,
+    /// Rows at the bottom of the terminal to draw in, or `None` for all of them.
+    height: Option<Height>,
     cmd: Option<String>,
 }
 
+impl Archived {
+    fn query(&self) -> String {
+        self.text_editor_snapshot
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string()
+    }
+
+    fn original(&self, index: usize) -> String {
+        self.lines.init().listbox.items()[index].to_string()
+    }
+
+    /// Returns the extractor of the current query while only the captures are shown.
+    fn extractor(&self) -> Option<Extractor> {
+        self.only_captures
+            .then(|| Extractor::new(&self.query(), self.case_insensitive))
+            .flatten()
+    }
+
+    /// Returns the line in the original queue to print,
+    /// or what it captures (if anything) with an extractor.
+    fn output(&self, index: usize, extractor: Option<&Extractor>) -> Option<String> {
+        let line = self.original(index);
+        match extractor {
+            Some(extractor) => extractor.extract(&line),
+            None => Some(line),
+        }
+    }
+
+    /// Filters the original queue by the current query,
+    /// optionally keeping the cursor at the same position.
+    fn refresh(&mut self, keep_position: bool) {
+        let query = self.query();
+        let table = &self.table;
+        let time_range = &self.time_range;
+        let hidden_pods = &self.hidden_pods;
+        let extractor = self.extractor();
+
+        let (indices, list): (Vec<usize>, Vec<StyledGraphemes>) =
+            self.queue
+                .par_iter()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    if table.as_ref().is_some_and(|table| table.is_header_line(i)) {
+                        return None;
+                    }
+                    if let Some(range) = time_range {
+                        if !self.times[i].is_some_and(|time| range.admits(time)) {
+                            return None;
+                        }
+                    }
+                    if self.pods[i]
+                        .as_ref()
+                        .is_some_and(|pod| hidden_pods.contains(pod))
+                    {
+                        return None;
+                    }
+                    let styled = match (&extractor, table.as_ref().and_then(|table| table.row(i))) {
+                        // Only the captures are shown, in place of the rows too.
+                        (Some(extractor), _) => self
+                            .levels
+                            .admits(line.level())
+                            .then(|| extractor.extract(&line.text))
+                            .flatten()
+                            .map(StyledGraphemes::from),
+                        // Rows are filtered by their lines, but highlighted where they match themselves.
+                        (None, Some(_)) if !self.levels.admits(line.level()) => None,
+                        (None, Some(row)) => sig::find(&query, &line.text, self.case_insensitive)
+                            .map(|_| {
+                                let matches = sig::find(&query, &row, self.case_insensitive)
+                                    .unwrap_or_default();
+                                sig::highlight(&row, &matches, self.highlight_style)
+                            }),
+                        (None, None) => sig::styled(
+                            &query,
+                            line,
+                            self.highlight_style,
+                            &self.fields,
+                            &self.levels,
+                            self.case_insensitive,
+                        ),
+                    };
+                    styled.map(|styled| {
+                        let styled = if self.marked.contains(&i) {
+                            styled.apply_attribute(Attribute::Reverse)
+                        } else {
+                            styled
+                        };
+                        (i, styled)
+                    })
+                })
+                .unzip();
+
+        let position = self.lines.after().listbox.position();
+        let mut listbox = listbox::Listbox::from_styled_graphemes(list);
+        if keep_position {
+            for _ in 0..position {
+                listbox.forward();
+            }
+        }
+
+        self.indices = indices;
+        self.lines.after_mut().listbox = listbox;
+        if self.stats.is_some() {
+            self.count_stats();
+        }
+    }
+
+    /// Counts the matches of the query over all the lines by their timestamps.
+    fn count_stats(&mut self) {
+        let lines = self.lines.init().listbox.items();
+        let mut stats = Stats::new(&self.query(), self.case_insensitive, lines.len());
+        for (line, time) in lines.iter().zip(&self.times) {
+            stats.push(&line.to_string(), *time);
+        }
+        self.stats = Some(stats);
+    }
+
+    fn toggle_stats(&mut self) {
+        if self.stats.take().is_none() {
+            self.count_stats();
+        }
+    }
+
+    /// Shows only what the query captures of the lines, or the whole lines again.
+    fn toggle_captures(&mut self) {
+        self.only_captures = !self.only_captures;
+        self.notice = Some(
+            if self.only_captures {
+                "Showing only the captures of the query"
+            } else {
+                "Showing whole lines"
+            }
+            .to_string(),
+        );
+        self.refresh(false);
+    }
+
+    fn open_help(&mut self) {
+        let mut help = Help::new("archived")
+            .settings(Settings {
+                case_insensitive: self.case_insensitive,
+                levels: &self.levels,
+                truncate: None,
+                only_captures: self.only_captures,
+                stats: self.stats.is_some(),
+                dedupe: self.dedupe,
+                hidden_pods: Some(&self.hidden_pods),
+                time_range: Some(self.time_range.as_ref()),
+                normal: self.vi.is_normal(),
+            })
+            .keybindings("Keys", &self.keybindings.query)
+            .keybindings(
+                "Keys in the prompts, lists and help opened from the query",
+                &self.keybindings.prompts,
+            );
+        if self.vi.is_normal() {
+            help = help.section(
+                "Keys in normal mode",
+                vi::COMMANDS
+                    .iter()
+                    .copied()
+                    .chain([("j, k", "Select the next or previous line")]),
+            );
+        }
+        self.help = Some(help);
+        self.keymap.switch("help");
+    }
+
+    fn close_help(&mut self) {
+        self.help = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Restores the query before the last change, filtering the lines by it again,
+    /// or the query undone last if `redo`.
+    fn undo(&mut self, redo: bool) {
+        let state = self.text_editor_snapshot.after_mut();
+        let restored = if redo {
+            self.undo.redo(state)
+        } else {
+            self.undo.undo(state)
+        };
+        if restored {
+            self.refresh(false);
+        }
+    }
+
+    /// Marks or unmarks the line at the cursor, then moves to the next one.
+    fn toggle_mark(&mut self) {
+        if let Some(&index) = self.indices.get(self.lines.after().listbox.position()) {
+            if !self.marked.remove(&index) {
+                self.marked.insert(index);
+            }
+            self.refresh(true);
+            self.lines.after_mut().listbox.forward();
+        }
+    }
+
+    /// Chooses the marked lines, or the line at the cursor if none are marked,
+    /// or only what they capture while only the captures are shown.
+    fn choose_selected(&mut self) {
+        let extractor = self.extractor();
+        let chosen = if self.marked.is_empty() {
+            self.indices
+                .get(self.lines.after().listbox.position())
+                .and_then(|&index| self.output(index, extractor.as_ref()))
+                .into_iter()
+                .collect()
+        } else {
+            self.marked
+                .iter()
+                .filter_map(|&index| self.output(index, extractor.as_ref()))
+                .collect()
+        };
+        self.chosen = Some(chosen);
+    }
+
+    /// Chooses all lines matching the current query,
+    /// or only what they capture while only the captures are shown.
+    fn choose_filtered(&mut self) {
+        let extractor = self.extractor();
+        self.chosen = Some(
+            self.indices
+                .iter()
+                .filter_map(|&index| self.output(index, extractor.as_ref()))
+                .collect(),
+        );
+    }
+
+    fn start_search(&mut self) {
+        self.search = Some(history::Search::start(
+            self.text_editor_snapshot.after_mut(),
+        ));
+        self.keymap.switch("search");
+    }
+
+    fn finish_search(&mut self, accept: bool) {
+        if let Some(search) = self.search.take() {
+            let state = self.text_editor_snapshot.after_mut();
+            if accept {
+                search.accept(state);
+            } else {
+                search.cancel(state);
+            }
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    fn start_export(&mut self) {
+        self.export = Some(export::Export::new(text_editor::State {
+            texteditor: Default::default(),
+            ..self.text_editor_snapshot.after().clone()
+        }));
+        self.keymap.switch("export");
+    }
+
+    fn cancel_export(&mut self) {
+        self.export = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Writes the filtered lines (or all of them) to the file entered in the prompt,
+    /// or only what they capture while only the captures are shown.
+    fn finish_export(&mut self) {
+        if let Some(export) = self.export.take() {
+            let query = self.query();
+            let extractor = self.extractor();
+            let indices: Vec<usize> = if export.all {
+                (0..self.lines.init().listbox.items().len()).collect()
+            } else {
+                self.indices.clone()
+            };
+            let lines = indices.into_iter().filter_map(|index| match &extractor {
+                // The captures are written as they are, with nothing to highlight.
+                Some(extractor) => extractor
+                    .extract(&self.original(index))
+                    .map(|captured| (captured, vec![])),
+                None => {
+                    let line = self.original(index);
+                    let matches =
+                        sig::find(&query, &line, self.case_insensitive).unwrap_or_default();
+                    Some((line, matches))
+                }
+            });
+
+            self.notice = Some(match export.write(lines, self.highlight_style) {
+                Ok(path) => format!("Exported to {}", path.display()),
+                Err(e) => format!("Failed to export: {}", e),
+            });
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Raises the minimum level of the lines listed, telling the one set.
+    fn cycle_level(&mut self) {
+        self.levels.cycle_minimum();
+        self.notice = Some(format!("Showing {}", self.levels.describe_minimum()));
+        self.refresh(false);
+    }
+
+    /// Opens the prompt for the time filter, filled with the one in effect.
+    fn start_time_range(&mut self) {
+        let text = self
+            .time_range
+            .as_ref()
+            .map(|range| range.text.clone())
+            .unwrap_or_default();
+        self.time_prompt = Some(text_editor::State {
+            texteditor: text_editor::TextEditor::new(text),
+            prefix: String::from("time ❯ "),
+            ..self.text_editor_snapshot.after().clone()
+        });
+        self.keymap.switch("time");
+    }
+
+    fn cancel_time_range(&mut self) {
+        self.time_prompt = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Filters the lines by the time filter entered in the prompt, or stops if it is empty.
+    fn finish_time_range(&mut self) {
+        if let Some(prompt) = self.time_prompt.take() {
+            let text = prompt.texteditor.text_without_cursor().to_string();
+            if text.trim().is_empty() {
+                self.time_range = None;
+            } else {
+                match timestamp::Range::parse(&text) {
+                    Ok(range) => self.time_range = Some(range),
+                    Err(e) => self.notice = Some(format!("Invalid time filter: {}", e)),
+                }
+            }
+            self.refresh(false);
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Opens the picker of the pods to show, if any line has a pod prefix.
+    fn start_pods(&mut self) {
+        self.pod_picker = pods::Picker::new(
+            self.pods.iter().flatten().map(String::as_str),
+            &self.hidden_pods,
+        );
+        match self.pod_picker {
+            Some(_) => self.keymap.switch("pods"),
+            None => {
+                self.notice = Some(String::from(
+                    "No pod prefixes of stern or kubectl logs --prefix in the lines",
+                ))
+            }
+        }
+    }
+
+    fn cancel_pods(&mut self) {
+        self.pod_picker = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Filters out the lines of the pods unchecked in the picker.
+    fn finish_pods(&mut self) {
+        if let Some(picker) = self.pod_picker.take() {
+            self.hidden_pods = picker.hidden();
+            self.refresh(false);
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Opens the patterns of all the lines, the most common first.
+    fn start_patterns(&mut self) {
+        let originals = self.originals();
+        self.patterns = patterns::Patterns::new(originals.iter().map(String::as_str));
+        if self.patterns.is_some() {
+            self.keymap.switch("patterns");
+        }
+    }
+
+    fn close_patterns(&mut self) {
+        self.patterns = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Replaces the query with a regular expression matching the lines of the selected pattern.
+    fn filter_by_pattern(&mut self) {
+        if let Some(patterns) = self.patterns.take() {
+            let state = self.text_editor_snapshot.after_mut();
+            history::commit(state);
+            state.texteditor = text_editor::TextEditor::new(patterns.selected().regex());
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Returns the lines of the original queue.
+    fn originals(&self) -> Vec<String> {
+        self.lines
+            .init()
+            .listbox
+            .items()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Opens the prompt for the fields to show as columns,
+    /// filled with the ones shown or else all the fields of the records.
+    fn start_columns(&mut self) {
+        let columns = match &self.table {
+            Some(table) => table.columns.clone(),
+            None => table::Table::fields(&self.originals()),
+        };
+        self.columns = Some(text_editor::State {
+            texteditor: text_editor::TextEditor::new(columns.join(",")),
+            prefix: String::from("columns ❯ "),
+            ..self.text_editor_snapshot.after().clone()
+        });
+        self.keymap.switch("columns");
+    }
+
+    fn cancel_columns(&mut self) {
+        self.columns = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Shows the fields entered in the prompt as columns, or the lines again if none are.
+    fn finish_columns(&mut self) {
+        if let Some(columns) = self.columns.take() {
+            let columns: Vec<String> = columns
+                .texteditor
+                .text_without_cursor()
+                .to_string()
+                .split(',')
+                .map(|column| column.trim().to_string())
+                .filter(|column| !column.is_empty())
+                .collect();
+            self.table = if columns.is_empty() {
+                None
+            } else {
+                let table = table::Table::new(&self.originals(), columns);
+                if table.is_none() {
+                    self.notice = Some(String::from(
+                        "No JSON, logfmt, CSV or TSV records to show as columns",
+                    ));
+                }
+                table
+            };
+            self.refresh(false);
+        }
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Shows the selected line together with `context_lines` lines
+    /// before and after it in the original (unfiltered) queue.
+    fn expand(&mut self) {
+        let Some(&index) = self.indices.get(self.lines.after().listbox.position()) else {
+            return;
+        };
+
+        let query = self.query();
+        let start = index.saturating_sub(self.context_lines);
+        let end = (index + self.context_lines + 1).min(self.queue.len());
+
+        let list: Vec<StyledGraphemes> = self.queue[start..end]
+            .iter()
+            .map(|line| {
+                sig::styled(
+                    &query,
+                    line,
+                    self.highlight_style,
+                    &self.fields,
+                    &self.levels,
+                    self.case_insensitive,
+                )
+                .unwrap_or_else(|| StyledGraphemes::from(line.text.as_str()))
+            })
+            .collect();
+
+        let mut listbox = listbox::Listbox::from_styled_graphemes(list);
+        for _ in start..index {
+            listbox.forward();
+        }
+
+        self.context = Some(listbox::State {
+            listbox,
+            ..self.lines.after().clone()
+        });
+        self.context_start = start;
+        self.keymap.switch("context");
+    }
+
+    /// Returns the gutter of the line at the position in the original queue.
+    fn gutter(&self, index: usize) -> StyledGraphemes {
+        self.gutter.render(&self.queue[index])
+    }
+
+    /// Returns the lines listed from the cursor down with their gutters,
+    /// rendered only for the lines in view.
+    fn create_lines_pane(&self, width: u16, height: u16) -> Pane {
+        let state = self.lines.after();
+        let position = state.listbox.position();
+        let visible: Vec<StyledGraphemes> = state
+            .listbox
+            .items()
+            .iter()
+            .zip(&self.indices)
+            .skip(position)
+            .take(height as usize)
+            .map(|(item, &index)| StyledGraphemes::from_iter([&self.gutter(index), item]))
+            .collect();
+        listbox::State {
+            listbox: listbox::Listbox::from_styled_graphemes(visible),
+            cursor: state.cursor.clone(),
+            active_item_style: state.active_item_style,
+            inactive_item_style: state.inactive_item_style,
+            lines: state.lines,
+        }
+        .create_pane(width, height)
+    }
+
+    /// Returns the selected line pretty-printed, taking up to `height` rows below a rule.
+    fn create_preview_pane(&self, width: u16, height: u16) -> Pane {
+        let dim = ContentStyle {
+            attributes: Attribute::Dim.into(),
+            ..Default::default()
+        };
+        let Some(&index) = self.indices.get(self.lines.after().listbox.position()) else {
+            return Pane::new(vec![], 0);
+        };
+        let original = self.original(index);
+        let text = json::pretty(&original[pod::message_start(&original)..])
+            .unwrap_or_else(|| String::from("(the selected line is not a JSON record)"));
+
+        let mut rows = vec![StyledGraphemes::from_str("─".repeat(width as usize), dim)];
+        for line in text.lines() {
+            rows.extend(
+                StyledGraphemes::from(line)
+                    .matrixify(width as usize, height as usize, 0)
+                    .0,
+            );
+        }
+        rows.truncate(height as usize);
+        Pane::new(rows, 0)
+    }
+
+    /// Returns to the filtered list, keeping the cursor where it was.
+    fn collapse(&mut self) {
+        self.context = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+}
+
+/// Unlike `listbox::State`, which only renders items from the cursor downward,
+/// this renders the lines above the cursor as well so the context stays visible.
+fn create_context_pane(
+    context: &listbox::State,
+    gutters: &[StyledGraphemes],
+    width: u16,
+    height: u16,
+) -> Pane {
+    let position = context.listbox.position();
+    let mut cursor_row = 0;
+    let mut layout = vec![];
+
+    for (i, (item, gutter)) in context.listbox.items().iter().zip(gutters).enumerate() {
+        let prefix = if i == position {
+            cursor_row = layout.len();
+            context.cursor.clone()
+        } else {
+            " ".repeat(StyledGraphemes::from(&context.cursor).widths())
+        };
+        let row = StyledGraphemes::from_iter([&StyledGraphemes::from(prefix), gutter, item]);
+        layout.extend(row.matrixify(width as usize, height as usize, 0).0);
+    }
+
+    // Reserve a row for the text editor pane.
+    let offset = (cursor_row + 2).saturating_sub(height as usize);
+    Pane::new(layout, offset)
+}
+
 impl promkit::Finalizer for Archived {
-    type Return = ();
+    /// Lines to print, or `None` when leaving to retry the command.
+    type Return = Option<Vec<String>>;
 
     fn finalize(&mut self) -> anyhow::Result<Self::Return> {
-        Ok(())
+        Ok(self.chosen.take())
     }
 }
 
 impl promkit::Renderer for Archived {
     fn create_panes(&self, width: u16, height: u16) -> Vec<Pane> {
+        let height = self.height.map_or(height, |h| h.rows(height));
+        let prompt = self.text_editor_snapshot.create_pane(width, height);
+        let footer = match (&self.export, &self.columns, &self.time_prompt, &self.notice) {
+            (Some(export), _, _, _) => export.path.create_pane(width, height),
+            (None, Some(columns), _, _) => columns.create_pane(width, height),
+            (None, None, Some(time), _) => time.create_pane(width, height),
+            (None, None, None, Some(notice)) => text::State {
+                text: notice.clone(),
+                style: Default::default(),
+            }
+            .create_pane(width, height),
+            (None, None, None, None) => Pane::new(vec![], 0),
+        };
+        let overlaid = self.help.is_some() || self.pod_picker.is_some() || self.patterns.is_some();
+        let header = match (&self.table, overlaid, &self.context) {
+            (Some(table), false, None) => {
+                // Indented as the rows are by the cursor and the gutters.
+                let indent = StyledGraphemes::from(" ".repeat(
+                    StyledGraphemes::from(&self.lines.after().cursor).widths()
+                        + self.queue.first().map_or(0, |_| self.gutter(0).widths()),
+                ));
+                let header = StyledGraphemes::from_iter([
+                    &indent,
+                    &table.header(ContentStyle {
+                        attributes: [Attribute::Bold, Attribute::Underlined].as_slice().into(),
+                        ..Default::default()
+                    }),
+                ]);
+                Pane::new(header.matrixify(width as usize, 1, 0).0, 0)
+            }
+            _ => Pane::new(vec![], 0),
+        };
+        let preview = if self.preview && !overlaid && self.context.is_none() {
+            self.create_preview_pane(width, height / 2)
+        } else {
+            Pane::new(vec![], 0)
+        };
+        let stats = match &self.stats {
+            Some(stats) if !overlaid => Pane::new(stats.rows(width, self.highlight_style), 0),
+            _ => Pane::new(vec![], 0),
+        };
+        // The lines get the rows left by the others, so as not to draw beyond the height.
+        let rows = height
+            .saturating_sub(
+                (prompt.visible_row_count()
+                    + footer.visible_row_count()
+                    + header.visible_row_count()
+                    + preview.visible_row_count()
+                    + stats.visible_row_count()) as u16,
+            )
+            .max(1);
+        let main = match (&self.help, &self.pod_picker, &self.patterns, &self.context) {
+            (Some(help), _, _, _) => help.create_pane(width, rows),
+            (None, Some(picker), _, _) => picker.create_pane(width, rows),
+            (None, None, Some(patterns), _) => patterns.create_pane(width, rows),
+            (None, None, None, Some(context)) => {
+                let start = self.context_start;
+                let gutters: Vec<StyledGraphemes> = (start..start + context.listbox.items().len())
+                    .map(|index| self.gutter(index))
+                    .collect();
+                create_context_pane(context, &gutters, width, rows)
+            }
+            (None, None, None, None) => self.create_lines_pane(width, rows),
+        };
         vec![
-            self.lines.create_pane(width, height),
-            self.text_editor_snapshot.create_pane(width, height),
+            header,
+            Pane::new(main.extract(rows as usize), 0),
+            preview,
+            stats,
+            prompt,
+            footer,
         ]
     }
 
     fn evaluate(&mut self, event: &Event) -> anyhow::Result<PromptSignal> {
-        let signal = self.keymap.get()(
-            event,
-            &mut self.text_editor_snapshot,
-            &mut self.lines,
-            self.cmd.clone(),
-        );
-        if self
-            .text_editor_snapshot
-            .after()
-            .texteditor
-            .text_without_cursor()
-            != self
-                .text_editor_snapshot
-                .borrow_before()
-                .texteditor
-                .text_without_cursor()
-        {
-            let query = self
-                .text_editor_snapshot
-                .after()
-                .texteditor
-                .text_without_cursor()
-                .to_string();
-
-            let list: Vec<StyledGraphemes> = self
-                .lines
-                .init()
-                .listbox
-                .items()
-                .par_iter()
-                .filter_map(|line| {
-                    sig::styled(
-                        &query,
-                        &line.to_string(),
-                        self.highlight_style,
-                        self.case_insensitive,
-                    )
-                })
-                .collect();
-
-            self.lines.after_mut().listbox = listbox::Listbox::from_styled_graphemes(list);
+        self.notice = None;
+        let position = self.text_editor_snapshot.after().texteditor.position();
+        let keymap = *self.keymap.get();
+        let signal = keymap(event, self);
+        if self.undo.changed(self.text_editor_snapshot.after()) {
+            self.undo
+                .record(self.text_editor_snapshot.after(), position);
+            self.refresh(false);
         }
         signal
     }
 }
 
+/// Returns the bindings of the preset with the given actions rebound.
+pub fn keybindings(
+    preset: Preset,
+    overrides: &HashMap<String, Vec<String>>,
+) -> anyhow::Result<ModeKeybindings<keymap::Action, keymap::PromptAction>> {
+    keymap::keybindings(preset)
+        .configure(overrides)
+        .map_err(|e| anyhow::anyhow!("invalid archived keybindings: {}", e))
+}
+
+/// Options of archived mode, mostly as given on the command line.
+#[derive(Clone)]
+pub struct Options<'a> {
+    /// Styles of the lines listed, such as the cursor before the selected one.
+    pub theme: &'a Theme,
+    pub gutter: Gutter,
+    pub highlight_style: ContentStyle,
+    pub fields: logfmt::Fields,
+    pub levels: Levels,
+    pub case_insensitive: bool,
+    pub dedupe: Option<Dedupe>,
+    pub only_captures: bool,
+    pub context_lines: usize,
+    pub columns: Vec<String>,
+    pub timestamps: timestamp::Parser,
+    /// Rows at the bottom of the terminal to draw in, or `None` for all of them.
+    pub height: Option<Height>,
+    pub keybindings: ModeKeybindings<keymap::Action, keymap::PromptAction>,
+    pub cmd: Option<String>,
+}
+
 pub fn run(
     text_editor: text_editor::State,
-    lines: listbox::State,
-    highlight_style: ContentStyle,
-    case_insensitive: bool,
-    cmd: Option<String>,
-) -> anyhow::Result<()> {
-    Prompt {
-        renderer: Archived {
-            keymap: ActiveKeySwitcher::new("default", keymap::default),
-            text_editor_snapshot: Snapshot::new(text_editor),
-            lines: Snapshot::new(lines),
-            highlight_style,
-            case_insensitive,
-            cmd,
-        },
-    }
-    .run()
+    queue: Vec<gutter::Line>,
+    options: Options,
+) -> anyhow::Result<Option<Vec<String>>> {
+    let Options {
+        theme,
+        gutter,
+        highlight_style,
+        fields,
+        levels,
+        case_insensitive,
+        dedupe,
+        only_captures,
+        context_lines,
+        columns,
+        timestamps,
+        height,
+        keybindings,
+        cmd,
+    } = options;
+    let query = text_editor.texteditor.text_without_cursor().to_string();
+    let lines = theme.listbox(queue.iter().map(|line| line.text.clone()));
+    let mut renderer = Archived {
+        keymap: ActiveKeySwitcher::new("default", keymap::default as keymap::Keymap)
+            .register("context", keymap::context)
+            .register("export", keymap::export)
+            .register("columns", keymap::columns)
+            .register("time", keymap::time)
+            .register("pods", keymap::pods)
+            .register("patterns", keymap::patterns)
+            .register("normal", keymap::normal)
+            .register("search", keymap::search)
+            .register("help", keymap::help),
+        keybindings,
+        text_editor_snapshot: Snapshot::new(text_editor),
+        indices: vec![],
+        lines: Snapshot::new(lines),
+        queue,
+        gutter,
+        marked: BTreeSet::new(),
+        chosen: None,
+        context: None,
+        context_start: 0,
+        context_lines,
+        preview: false,
+        export: None,
+        table: None,
+        columns: None,
+        times: vec![],
+        time_range: None,
+        time_prompt: None,
+        pods: vec![],
+        hidden_pods: BTreeSet::new(),
+        pod_picker: None,
+        patterns: None,
+        stats: None,
+        only_captures,
+        search: None,
+        vi: Vi::default(),
+        killed: String::new(),
+        undo: Undo::new(&query),
+        help: None,
+        notice: None,
+        highlight_style,
+        fields,
+        levels,
+        case_insensitive,
+        dedupe,
+        height,
+        cmd,
+    };
+    let mut last = None;
+    renderer.times = renderer
+        .originals()
+        .iter()
+        .map(|line| {
+            last = timestamps.parse(line).or(last);
+            last
+        })
+        .collect();
+    renderer.pods = renderer
+        .originals()
+        .iter()
+        .map(|line| pod::parse(line).map(|prefix| prefix.pod(line).to_string()))
+        .collect();
+    if !columns.is_empty() {
+        renderer.table = table::Table::new(&renderer.originals(), columns);
+    }
+    // Apply the initial query, if any.
+    renderer.refresh(false);
+
+    Prompt { renderer }.run()
 }
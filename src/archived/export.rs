@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::ValueEnum;
+use grep::matcher::Match;
+use promkit::{crossterm::style::ContentStyle, text_editor};
+
+use crate::output::Format;
+
+/// Prompt for the file to write lines to.
+pub struct Export {
+    pub path: text_editor::State,
+    pub format: Format,
+    /// Whether to write the entire retained queue instead of the filtered lines.
+    pub all: bool,
+}
+
+impl Export {
+    pub fn new(path: text_editor::State) -> Self {
+        let mut ret = Self {
+            path,
+            format: Format::default(),
+            all: false,
+        };
+        ret.update_prefix();
+        ret
+    }
+
+    pub fn cycle_format(&mut self) {
+        self.format = match self.format {
+            Format::Plain => Format::Highlight,
+            Format::Highlight => Format::Json,
+            Format::Json => Format::Plain,
+        };
+        self.update_prefix();
+    }
+
+    pub fn toggle_scope(&mut self) {
+        self.all = !self.all;
+        self.update_prefix();
+    }
+
+    fn update_prefix(&mut self) {
+        self.path.prefix = format!(
+            "export {} lines as {} to ❯ ",
+            if self.all { "all" } else { "filtered" },
+            self.format
+                .to_possible_value()
+                .map(|v| v.get_name().to_string())
+                .unwrap_or_default(),
+        );
+    }
+
+    /// Writes the lines with their matches, returning the path written to.
+    pub fn write<I: IntoIterator<Item = (String, Vec<Match>)>>(
+        &self,
+        lines: I,
+        highlight_style: ContentStyle,
+    ) -> anyhow::Result<PathBuf> {
+        let path = self.path.texteditor.text_without_cursor().to_string();
+        let path = match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => PathBuf::from(path),
+        };
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for (line, matches) in lines {
+            writeln!(
+                writer,
+                "{}",
+                self.format.render(&line, &matches, highlight_style)
+            )?;
+        }
+        writer.flush()?;
+        Ok(path)
+    }
+}
@@ -1,111 +1,403 @@
 use promkit::{
     crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers},
-    listbox,
-    snapshot::Snapshot,
     text_editor, PromptSignal,
 };
 
-pub type Keymap = fn(
-    &Event,
-    &mut Snapshot<text_editor::State>,
-    &mut Snapshot<listbox::State>,
-    Option<String>,
-) -> anyhow::Result<PromptSignal>;
+use super::Archived;
+use crate::{
+    editing, history,
+    keybinding::{KeyChord, Keybindings, ModeKeybindings, Preset},
+};
+
+pub type Keymap = fn(&Event, &mut Archived) -> anyhow::Result<PromptSignal>;
+
+/// Actions which can be bound to keys in archived mode.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Action {
+    /// Exit archived mode
+    Exit,
+    /// Go back to streaming mode and retry the command (only with `--cmd`)
+    Retry,
+    /// Show the lines around the selected one
+    Expand,
+    /// Show or hide the selected JSON record pretty-printed below the lines
+    TogglePreview,
+    /// Pick fields of the records to show as columns
+    Columns,
+    /// Raise the minimum level of the lines shown, back to all after error
+    CycleLevel,
+    /// Filter the lines by their timestamps, e.g. `last 5m`, in the prompt
+    TimeRange,
+    /// Pick the pods of stern or `kubectl logs --prefix` whose lines are shown
+    Pods,
+    /// List the patterns of the lines by how common they are, to filter by one
+    Patterns,
+    /// Show or hide the counts of the matches over time and of the captured values
+    ToggleStats,
+    /// Show and print only what the capture groups of the query capture, or whole lines again
+    ToggleCaptures,
+    /// Export lines to a file
+    Export,
+    /// Recall the previous query from the history
+    HistoryPrevious,
+    /// Recall the next query from the history
+    HistoryNext,
+    /// Search the history
+    SearchHistory,
+    /// Exit and print the marked lines, or the selected one
+    Choose,
+    /// Exit and print all the filtered lines
+    ChooseFiltered,
+    /// Mark or unmark the selected line
+    ToggleMark,
+    /// Move the cursor one character to the left
+    Backward,
+    /// Move the cursor one character to the right
+    Forward,
+    /// Move the cursor to the start of the query
+    MoveToHead,
+    /// Move the cursor to the end of the query
+    MoveToTail,
+    /// Select the previous line
+    SelectPrevious,
+    /// Select the next line
+    SelectNext,
+    /// Move the cursor to the previous word
+    WordBackward,
+    /// Move the cursor to the next word
+    WordForward,
+    /// Delete the character before the cursor
+    Erase,
+    /// Delete the whole query
+    EraseAll,
+    /// Delete the character at the cursor
+    Delete,
+    /// Delete the word before the cursor, keeping it for yanking
+    EraseWord,
+    /// Delete the word after the cursor, keeping it for yanking
+    EraseNextWord,
+    /// Delete from the cursor to the end, keeping it for yanking
+    KillToEnd,
+    /// Insert the text deleted last
+    Yank,
+    /// Undo the last change to the query, along with the lines it filtered
+    Undo,
+    /// Redo the change undone last
+    Redo,
+    /// Switch to vi-style normal mode
+    NormalMode,
+    /// Show the key bindings and settings
+    Help,
+}
+
+/// Actions which can be bound to keys in the prompts and lists opened from archived mode,
+/// sharing the keys of the query's actions (e.g. `exit` and `erase`) where they do the same.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PromptAction {
+    /// Apply the prompt, e.g. export the lines or filter by the selected pattern
+    Confirm,
+    /// Close the prompt without applying it, or the lines around the selected one
+    Cancel,
+    /// Select the previous line, pod or pattern, or scroll the help up
+    Previous,
+    /// Select the next line, pod or pattern, or scroll the help down
+    Next,
+    /// Cycle the format of the export among plain, highlight (ANSI) and json
+    CycleFormat,
+    /// Switch the export between the filtered lines and all retained lines
+    ToggleScope,
+    /// Show or hide the lines of the selected pod
+    TogglePod,
+    /// Show the lines of all the pods, or hide them all if all are shown
+    ToggleAllPods,
+}
 
-pub fn default(
+pub fn keybindings(preset: Preset) -> ModeKeybindings<Action, PromptAction> {
+    let common = [
+        (KeyChord::ctrl('c'), Action::Exit),
+        (KeyChord::ctrl('r'), Action::Retry),
+        (KeyChord::ctrl('v'), Action::Expand),
+        (KeyChord::alt('p'), Action::TogglePreview),
+        (KeyChord::alt('t'), Action::Columns),
+        (KeyChord::alt('l'), Action::CycleLevel),
+        (KeyChord::alt('r'), Action::TimeRange),
+        (KeyChord::alt('k'), Action::Pods),
+        (KeyChord::alt('g'), Action::Patterns),
+        (KeyChord::alt('s'), Action::ToggleStats),
+        (KeyChord::alt('o'), Action::ToggleCaptures),
+        (KeyChord::ctrl('x'), Action::Export),
+        (KeyChord::ctrl('p'), Action::HistoryPrevious),
+        (KeyChord::ctrl('n'), Action::HistoryNext),
+        (KeyChord::ctrl('s'), Action::SearchHistory),
+        (KeyChord::key(KeyCode::Enter), Action::Choose),
+        (KeyChord::ctrl('o'), Action::ChooseFiltered),
+        (KeyChord::key(KeyCode::Tab), Action::ToggleMark),
+        (KeyChord::key(KeyCode::Left), Action::Backward),
+        (KeyChord::key(KeyCode::Right), Action::Forward),
+        (KeyChord::key(KeyCode::Up), Action::SelectPrevious),
+        (KeyChord::key(KeyCode::Down), Action::SelectNext),
+        (KeyChord::alt('b'), Action::WordBackward),
+        (KeyChord::alt('f'), Action::WordForward),
+        (KeyChord::key(KeyCode::Backspace), Action::Erase),
+        (KeyChord::ctrl('u'), Action::EraseAll),
+        (KeyChord::key(KeyCode::Delete), Action::Delete),
+        (KeyChord::ctrl('w'), Action::EraseWord),
+        (KeyChord::alt('d'), Action::EraseNextWord),
+        (KeyChord::ctrl('k'), Action::KillToEnd),
+        (KeyChord::ctrl('y'), Action::Yank),
+        (KeyChord::ctrl('z'), Action::Undo),
+        (KeyChord::alt('z'), Action::Redo),
+        (KeyChord::key(KeyCode::F(1)), Action::Help),
+    ];
+    let editing = match preset {
+        Preset::Emacs => vec![
+            (KeyChord::ctrl('a'), Action::MoveToHead),
+            (KeyChord::ctrl('e'), Action::MoveToTail),
+        ],
+        Preset::Vi => vec![
+            (KeyChord::key(KeyCode::Home), Action::MoveToHead),
+            (KeyChord::key(KeyCode::End), Action::MoveToTail),
+            (KeyChord::key(KeyCode::Esc), Action::NormalMode),
+        ],
+    };
+    // Keys only the pod picker reads, where nothing is typed, may type characters.
+    let prompts = [
+        (KeyChord::key(KeyCode::Enter), PromptAction::Confirm),
+        (KeyChord::key(KeyCode::Esc), PromptAction::Cancel),
+        (KeyChord::key(KeyCode::Up), PromptAction::Previous),
+        (KeyChord::key(KeyCode::Down), PromptAction::Next),
+        (KeyChord::key(KeyCode::Tab), PromptAction::CycleFormat),
+        (KeyChord::key(KeyCode::BackTab), PromptAction::ToggleScope),
+        (KeyChord::key(KeyCode::Char(' ')), PromptAction::TogglePod),
+        (KeyChord::key(KeyCode::Tab), PromptAction::TogglePod),
+        (
+            KeyChord::key(KeyCode::Char('a')),
+            PromptAction::ToggleAllPods,
+        ),
+    ];
+    ModeKeybindings {
+        query: Keybindings::new(common.into_iter().chain(editing)),
+        prompts: Keybindings::new(prompts),
+    }
+}
+
+/// Returns which of the given actions of the prompts the key pressed is bound to,
+/// failing by the keys of `exit` to exit as from the query.
+fn prompt(
     event: &Event,
-    text_editor_snapshot: &mut Snapshot<text_editor::State>,
-    logs_snapshot: &mut Snapshot<listbox::State>,
-    cmd: Option<String>,
-) -> anyhow::Result<PromptSignal> {
-    let text_editor_state = text_editor_snapshot.after_mut();
-    let logs_state = logs_snapshot.after_mut();
+    keybindings: &ModeKeybindings<Action, PromptAction>,
+    actions: &[PromptAction],
+) -> anyhow::Result<Option<PromptAction>> {
+    if keybindings.query.get(event) == Some(Action::Exit) {
+        return Err(anyhow::anyhow!("ctrl+c"));
+    }
+    Ok(keybindings.prompts.get_among(event, actions))
+}
 
-    match event {
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('r'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }) => {
-            if cmd.is_some() {
-                // Exiting archive mode here allows
-                // the caller to re-enter streaming mode,
-                // as it is running in an infinite loop.
-                return Ok(PromptSignal::Quit);
-            }
+fn act(action: Action, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let text_editor_state = archived.text_editor_snapshot.after_mut();
+    let logs_state = archived.lines.after_mut();
+
+    match action {
+        Action::Exit => {
+            history::commit(text_editor_state);
+            return Err(anyhow::anyhow!("ctrl+c"));
+        }
+        Action::Retry if archived.cmd.is_some() => {
+            history::commit(text_editor_state);
+            // Exiting archive mode here allows
+            // the caller to re-enter streaming mode,
+            // as it is running in an infinite loop.
+            return Ok(PromptSignal::Quit);
         }
+        Action::Retry => (),
 
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('c'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }) => return Err(anyhow::anyhow!("ctrl+c")),
+        Action::Expand => archived.expand(),
+        Action::TogglePreview => archived.preview = !archived.preview,
+        Action::Columns => archived.start_columns(),
+        Action::CycleLevel => archived.cycle_level(),
+        Action::TimeRange => archived.start_time_range(),
+        Action::Pods => archived.start_pods(),
+        Action::Patterns => archived.start_patterns(),
+        Action::ToggleStats => archived.toggle_stats(),
+        Action::ToggleCaptures => archived.toggle_captures(),
+
+        Action::Export => {
+            history::commit(text_editor_state);
+            archived.start_export();
+        }
+
+        // History.
+        Action::HistoryPrevious => history::backward(text_editor_state),
+        Action::HistoryNext => history::forward(text_editor_state),
+        Action::SearchHistory => archived.start_search(),
+
+        // Choose line(s) to print on exit.
+        Action::Choose => {
+            history::commit(text_editor_state);
+            archived.choose_selected();
+            return Ok(PromptSignal::Quit);
+        }
+        Action::ChooseFiltered => {
+            history::commit(text_editor_state);
+            archived.choose_filtered();
+            return Ok(PromptSignal::Quit);
+        }
+        Action::ToggleMark => archived.toggle_mark(),
 
         // Move cursor (text editor)
+        Action::Backward => {
+            text_editor_state.texteditor.backward();
+        }
+        Action::Forward => {
+            text_editor_state.texteditor.forward();
+        }
+        Action::MoveToHead => text_editor_state.texteditor.move_to_head(),
+        Action::MoveToTail => text_editor_state.texteditor.move_to_tail(),
+        Action::WordBackward => text_editor_state
+            .texteditor
+            .move_to_previous_nearest(&text_editor_state.word_break_chars),
+        Action::WordForward => text_editor_state
+            .texteditor
+            .move_to_next_nearest(&text_editor_state.word_break_chars),
+
+        // Move cursor (listbox).
+        Action::SelectPrevious => {
+            logs_state.listbox.backward();
+        }
+        Action::SelectNext => {
+            logs_state.listbox.forward();
+        }
+
+        // Erase char(s).
+        Action::Erase => text_editor_state.texteditor.erase(),
+        Action::EraseAll => text_editor_state.texteditor.erase_all(),
+        Action::Delete => editing::delete(text_editor_state),
+        Action::EraseWord => {
+            archived.killed = editing::kill(text_editor_state, |state| {
+                state
+                    .texteditor
+                    .erase_to_previous_nearest(&state.word_break_chars)
+            })
+        }
+        Action::EraseNextWord => {
+            archived.killed = editing::kill(text_editor_state, |state| {
+                state
+                    .texteditor
+                    .erase_to_next_nearest(&state.word_break_chars)
+            })
+        }
+        Action::KillToEnd => {
+            archived.killed = editing::kill(text_editor_state, editing::erase_to_end)
+        }
+        Action::Yank => editing::yank(text_editor_state, &archived.killed),
+
+        Action::Undo => archived.undo(false),
+        Action::Redo => archived.undo(true),
+
+        Action::NormalMode => {
+            archived.vi.enter_normal(text_editor_state);
+            archived.keymap.switch("normal");
+        }
+        Action::Help => archived.open_help(),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn default(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    if let Some(action) = archived.keybindings.query.get(event) {
+        return act(action, archived);
+    }
+
+    let text_editor_state = archived.text_editor_snapshot.after_mut();
+    match event {
+        // A query cannot start with `?`, so it shows the help instead.
         Event::Key(KeyEvent {
-            code: KeyCode::Left,
-            modifiers: KeyModifiers::NONE,
+            code: KeyCode::Char('?'),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => {
-            text_editor_state.texteditor.backward();
+        }) if text_editor_state
+            .texteditor
+            .text_without_cursor()
+            .is_empty() =>
+        {
+            archived.open_help()
         }
+
+        // Input char.
         Event::Key(KeyEvent {
-            code: KeyCode::Right,
+            code: KeyCode::Char(ch),
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => {
-            text_editor_state.texteditor.forward();
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('a'),
-            modifiers: KeyModifiers::CONTROL,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => text_editor_state.texteditor.move_to_head(),
+        }) => match text_editor_state.edit_mode {
+            text_editor::Mode::Insert => text_editor_state.texteditor.insert(*ch),
+            text_editor::Mode::Overwrite => text_editor_state.texteditor.overwrite(*ch),
+        },
+
+        _ => (),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn normal(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let text_editor_state = archived.text_editor_snapshot.after_mut();
+    let logs_state = archived.lines.after_mut();
+
+    match event {
         Event::Key(KeyEvent {
-            code: KeyCode::Char('e'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => text_editor_state.texteditor.move_to_tail(),
+        }) => archived.vi.cancel(),
 
         // Move cursor (listbox).
         Event::Key(KeyEvent {
-            code: KeyCode::Up,
+            code: KeyCode::Char('k'),
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
         }) => {
+            archived.vi.cancel();
             logs_state.listbox.backward();
         }
         Event::Key(KeyEvent {
-            code: KeyCode::Down,
+            code: KeyCode::Char('j'),
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
         }) => {
+            archived.vi.cancel();
             logs_state.listbox.forward();
         }
 
-        // Erase char(s).
         Event::Key(KeyEvent {
-            code: KeyCode::Backspace,
+            code: KeyCode::Char('u'),
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => text_editor_state.texteditor.erase(),
+        }) => {
+            archived.vi.cancel();
+            archived.undo(false);
+        }
         Event::Key(KeyEvent {
-            code: KeyCode::Char('u'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char('?'),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => text_editor_state.texteditor.erase_all(),
+        }) => {
+            archived.vi.cancel();
+            archived.open_help();
+        }
 
-        // Input char.
         Event::Key(KeyEvent {
             code: KeyCode::Char(ch),
             modifiers: KeyModifiers::NONE,
@@ -117,12 +409,287 @@ pub fn default(
             modifiers: KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => match text_editor_state.edit_mode {
-            text_editor::Mode::Insert => text_editor_state.texteditor.insert(*ch),
-            text_editor::Mode::Overwrite => text_editor_state.texteditor.overwrite(*ch),
-        },
+        }) if archived.vi.command(text_editor_state, *ch) => {
+            archived.keymap.switch(archived.vi.keymap())
+        }
+
+        // Keys other than the commands work as in insert mode, except for typing.
+        _ => {
+            if let Some(action) = archived.keybindings.query.get(event) {
+                return act(action, archived);
+            }
+        }
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn help(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let help = match archived.help.as_mut() {
+        Some(help) => help,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[PromptAction::Previous, PromptAction::Next],
+    )? {
+        Some(PromptAction::Previous) => help.scroll_up(),
+        Some(PromptAction::Next) => help.scroll_down(),
+        _ => {
+            if let Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
+                ..
+            }) = event
+            {
+                archived.close_help();
+            }
+        }
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn context(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let context_state = match archived.context.as_mut() {
+        Some(context_state) => context_state,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    // Expanding again, by whichever keys are bound to it, collapses the lines.
+    if archived.keybindings.query.get(event) == Some(Action::Expand) {
+        archived.collapse();
+        return Ok(PromptSignal::Continue);
+    }
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[
+            PromptAction::Cancel,
+            PromptAction::Previous,
+            PromptAction::Next,
+        ],
+    )? {
+        Some(PromptAction::Cancel) => archived.collapse(),
+
+        // Move cursor (listbox).
+        Some(PromptAction::Previous) => {
+            context_state.listbox.backward();
+        }
+        Some(PromptAction::Next) => {
+            context_state.listbox.forward();
+        }
 
         _ => (),
     }
     Ok(PromptSignal::Continue)
 }
+
+pub fn export(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let export = match archived.export.as_mut() {
+        Some(export) => export,
+        None => return Ok(PromptSignal::Continue),
+    };
+    let path_state = &mut export.path;
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[
+            PromptAction::Confirm,
+            PromptAction::Cancel,
+            PromptAction::CycleFormat,
+            PromptAction::ToggleScope,
+        ],
+    )? {
+        Some(PromptAction::Confirm) => archived.finish_export(),
+        Some(PromptAction::Cancel) => archived.cancel_export(),
+
+        // Switch format and scope.
+        Some(PromptAction::CycleFormat) => export.cycle_format(),
+        Some(PromptAction::ToggleScope) => export.toggle_scope(),
+
+        _ => edit(event, &archived.keybindings.query, path_state),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn columns(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let columns_state = match archived.columns.as_mut() {
+        Some(columns_state) => columns_state,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[PromptAction::Confirm, PromptAction::Cancel],
+    )? {
+        Some(PromptAction::Confirm) => archived.finish_columns(),
+        Some(PromptAction::Cancel) => archived.cancel_columns(),
+        _ => edit(event, &archived.keybindings.query, columns_state),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn time(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let time_state = match archived.time_prompt.as_mut() {
+        Some(time_state) => time_state,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[PromptAction::Confirm, PromptAction::Cancel],
+    )? {
+        Some(PromptAction::Confirm) => archived.finish_time_range(),
+        Some(PromptAction::Cancel) => archived.cancel_time_range(),
+        _ => edit(event, &archived.keybindings.query, time_state),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn pods(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let picker = match archived.pod_picker.as_mut() {
+        Some(picker) => picker,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[
+            PromptAction::Confirm,
+            PromptAction::Cancel,
+            PromptAction::Previous,
+            PromptAction::Next,
+            PromptAction::TogglePod,
+            PromptAction::ToggleAllPods,
+        ],
+    )? {
+        Some(PromptAction::Confirm) => archived.finish_pods(),
+        Some(PromptAction::Cancel) => archived.cancel_pods(),
+
+        // Move cursor.
+        Some(PromptAction::Previous) => picker.up(),
+        Some(PromptAction::Next) => picker.down(),
+
+        // Show or hide pods.
+        Some(PromptAction::TogglePod) => picker.toggle(),
+        Some(PromptAction::ToggleAllPods) => picker.toggle_all(),
+
+        _ => (),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn patterns(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let patterns = match archived.patterns.as_mut() {
+        Some(patterns) => patterns,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[
+            PromptAction::Confirm,
+            PromptAction::Cancel,
+            PromptAction::Previous,
+            PromptAction::Next,
+        ],
+    )? {
+        Some(PromptAction::Confirm) => archived.filter_by_pattern(),
+        Some(PromptAction::Cancel) => archived.close_patterns(),
+
+        // Move cursor.
+        Some(PromptAction::Previous) => patterns.up(),
+        Some(PromptAction::Next) => patterns.down(),
+
+        _ => (),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+/// Edits the text of a prompt other than the query, e.g. for the file to export to,
+/// by the keys of the query's actions to move the cursor and erase.
+fn edit(event: &Event, keybindings: &Keybindings<Action>, state: &mut text_editor::State) {
+    match keybindings.get(event) {
+        // Move cursor.
+        Some(Action::Backward) => {
+            state.texteditor.backward();
+        }
+        Some(Action::Forward) => {
+            state.texteditor.forward();
+        }
+        Some(Action::MoveToHead) => state.texteditor.move_to_head(),
+        Some(Action::MoveToTail) => state.texteditor.move_to_tail(),
+
+        // Erase char(s).
+        Some(Action::Erase) => state.texteditor.erase(),
+        Some(Action::EraseAll) => state.texteditor.erase_all(),
+
+        _ => match event {
+            // Input char.
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            })
+            | Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            }) => match state.edit_mode {
+                text_editor::Mode::Insert => state.texteditor.insert(*ch),
+                text_editor::Mode::Overwrite => state.texteditor.overwrite(*ch),
+            },
+
+            _ => (),
+        },
+    }
+}
+
+pub fn search(event: &Event, archived: &mut Archived) -> anyhow::Result<PromptSignal> {
+    let text_editor_state = archived.text_editor_snapshot.after_mut();
+    let search = match archived.search.as_mut() {
+        Some(search) => search,
+        None => return Ok(PromptSignal::Continue),
+    };
+
+    match prompt(
+        event,
+        &archived.keybindings,
+        &[PromptAction::Confirm, PromptAction::Cancel],
+    )? {
+        Some(PromptAction::Confirm) => archived.finish_search(true),
+        Some(PromptAction::Cancel) => archived.finish_search(false),
+
+        // Searching again, by whichever keys are bound to it, moves to an older match.
+        _ => match archived.keybindings.query.get(event) {
+            Some(Action::SearchHistory) => search.older(text_editor_state),
+            Some(Action::Erase) => search.pop(text_editor_state),
+
+            _ => match event {
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(ch),
+                    modifiers: KeyModifiers::NONE,
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::NONE,
+                })
+                | Event::Key(KeyEvent {
+                    code: KeyCode::Char(ch),
+                    modifiers: KeyModifiers::SHIFT,
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::NONE,
+                }) => search.push(text_editor_state, *ch),
+
+                _ => (),
+            },
+        },
+    }
+    Ok(PromptSignal::Continue)
+}
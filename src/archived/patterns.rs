@@ -0,0 +1,228 @@
+use std::{cmp::Reverse, collections::HashMap, sync::OnceLock};
+
+use promkit::{
+    crossterm::style::{Attribute, ContentStyle},
+    grapheme::StyledGraphemes,
+    pane::Pane,
+};
+use regex::Regex;
+
+/// What the variable parts of lines are masked as.
+const WILDCARD: &str = "<*>";
+
+/// Share of the tokens a line must have in common with a pattern to be of it.
+const SIMILARITY: f64 = 0.5;
+
+/// Runs of word characters with a digit in them, such as numbers, IDs, IPs,
+/// UUIDs and timestamps, taken as variable.
+fn variables() -> &'static Regex {
+    static VARIABLES: OnceLock<Regex> = OnceLock::new();
+    VARIABLES.get_or_init(|| Regex::new(r"[\w.:-]*\d[\w.:-]*").unwrap())
+}
+
+/// Returns the words of the line with their variable parts masked, e.g. `status=<*>`.
+fn tokens(line: &str) -> Vec<String> {
+    line.split_whitespace()
+        .map(|token| variables().replace_all(token, WILDCARD).into_owned())
+        .collect()
+}
+
+/// A template of lines with their variable parts masked, e.g. `GET /users/<*> took <*>`.
+pub struct Pattern {
+    tokens: Vec<String>,
+    count: usize,
+}
+
+impl Pattern {
+    /// Returns the share of the tokens the same as those of the pattern, wildcards included.
+    fn similarity(&self, tokens: &[String]) -> f64 {
+        if tokens.is_empty() {
+            return 1.0;
+        }
+        let same = self
+            .tokens
+            .iter()
+            .zip(tokens)
+            .filter(|(token, other)| *token == WILDCARD || token == other)
+            .count();
+        same as f64 / tokens.len() as f64
+    }
+
+    /// Takes in the tokens of a line, masking those it differs in.
+    fn merge(&mut self, tokens: Vec<String>) {
+        for (token, other) in self.tokens.iter_mut().zip(tokens) {
+            if *token != other {
+                *token = WILDCARD.to_string();
+            }
+        }
+        self.count += 1;
+    }
+
+    /// Returns a regular expression matching the lines of the pattern, as a query.
+    pub fn regex(&self) -> String {
+        let tokens: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|token| {
+                token
+                    .split(WILDCARD)
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(r"\S+")
+                    // Queries are split into alternatives by `|`.
+                    .replace(r"\|", r"\x7c")
+            })
+            .collect();
+        format!(r"^\s*{}\s*$", tokens.join(r"\s+"))
+    }
+
+    /// Returns the pattern with its wildcards in the style.
+    fn styled(&self, wildcard_style: ContentStyle) -> StyledGraphemes {
+        let text = self.tokens.join(" ");
+        let mut styled = StyledGraphemes::default();
+        for (i, part) in text.split(WILDCARD).enumerate() {
+            if i > 0 {
+                styled.extend(StyledGraphemes::from_str(WILDCARD, wildcard_style).0);
+            }
+            styled.extend(StyledGraphemes::from(part).0);
+        }
+        styled
+    }
+}
+
+/// Groups the lines into patterns after Drain: a line is of the most similar pattern
+/// with as many tokens if similar enough, or else starts a new one.
+fn cluster<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Pattern> {
+    let mut patterns: Vec<Pattern> = vec![];
+    let mut by_length: HashMap<usize, Vec<usize>> = HashMap::new();
+    for line in lines {
+        let tokens = tokens(line);
+        let candidates = by_length.entry(tokens.len()).or_default();
+        let best = candidates
+            .iter()
+            .map(|&i| (i, patterns[i].similarity(&tokens)))
+            .filter(|(_, similarity)| *similarity >= SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((i, _)) => patterns[i].merge(tokens),
+            None => {
+                candidates.push(patterns.len());
+                patterns.push(Pattern { tokens, count: 1 });
+            }
+        }
+    }
+    // The sort is stable, so patterns as common stay in the order they first appear.
+    patterns.sort_by_key(|pattern| Reverse(pattern.count));
+    patterns
+}
+
+/// Overlay listing the patterns of the lines, the most common first.
+pub struct Patterns {
+    patterns: Vec<Pattern>,
+    cursor: usize,
+}
+
+impl Patterns {
+    /// Returns the patterns of the lines, or `None` if there are no lines.
+    pub fn new<'a>(lines: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let patterns = cluster(lines);
+        (!patterns.is_empty()).then_some(Self {
+            patterns,
+            cursor: 0,
+        })
+    }
+
+    pub fn up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.patterns.len() - 1);
+    }
+
+    pub fn selected(&self) -> &Pattern {
+        &self.patterns[self.cursor]
+    }
+
+    pub fn create_pane(&self, width: u16, height: u16) -> Pane {
+        let dim = ContentStyle {
+            attributes: Attribute::Dim.into(),
+            ..Default::default()
+        };
+        let mut rows = vec![StyledGraphemes::from_str(
+            format!(
+                "{} patterns (enter: filter by the pattern, esc: close)",
+                self.patterns.len()
+            ),
+            dim,
+        )];
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            let cursor = if i == self.cursor { "❯ " } else { "  " };
+            let row = StyledGraphemes::from_iter([
+                StyledGraphemes::from(cursor),
+                StyledGraphemes::from_str(format!("{:>7}  ", pattern.count), dim),
+                pattern.styled(dim),
+            ]);
+            // One row each, so that the cursor can be kept in view.
+            rows.extend(row.matrixify(width as usize, 1, 0).0.into_iter().take(1));
+        }
+        // Scrolled for the cursor, below the title, to stay in view.
+        let offset = (self.cursor + 2).saturating_sub(height as usize);
+        Pane::new(rows, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_variable_tokens() {
+        assert_eq!(
+            tokens("GET /users/42 took 5ms from 10.0.0.1"),
+            ["GET", "/users/<*>", "took", "<*>", "from", "<*>"]
+        );
+    }
+
+    #[test]
+    fn clusters_lines_by_their_templates() {
+        let lines = [
+            "worker started",
+            "GET /users/1 took 5ms",
+            "connection reset by peer",
+            "GET /users/22 took 13ms",
+            "GET /orders/7 took 2ms",
+        ];
+        let patterns = cluster(lines.into_iter());
+        let templates: Vec<(String, usize)> = patterns
+            .iter()
+            .map(|pattern| (pattern.tokens.join(" "), pattern.count))
+            .collect();
+        assert_eq!(
+            templates,
+            [
+                ("GET <*> took <*>".to_string(), 3),
+                ("worker started".to_string(), 1),
+                ("connection reset by peer".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_dissimilar_lines_apart() {
+        let patterns = cluster(["user logged in", "disk almost full"].into_iter());
+        assert_eq!(patterns.len(), 2);
+    }
+
+    #[test]
+    fn matches_the_lines_of_the_pattern() {
+        let lines = ["GET /users/1 took 5ms", "GET /users/22 took 13ms"];
+        let patterns = cluster(lines.into_iter());
+        let regex = Regex::new(&patterns[0].regex()).unwrap();
+        assert!(lines.iter().all(|line| regex.is_match(line)));
+        assert!(!regex.is_match("POST /users/1 took 5ms"));
+
+        let patterns = cluster(["a|b 1"].into_iter());
+        assert!(!patterns[0].regex().contains('|'));
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeSet;
+
+use promkit::{
+    crossterm::style::{Attribute, ContentStyle},
+    grapheme::StyledGraphemes,
+    pane::Pane,
+};
+
+use crate::pod;
+
+/// Overlay listing the pods of the lines, each checked if its lines are shown.
+pub struct Picker {
+    /// Pods in the order they first appear, with their numbers of lines
+    /// and whether those are shown.
+    pods: Vec<(String, usize, bool)>,
+    cursor: usize,
+}
+
+impl Picker {
+    /// Returns the picker of the pods of the lines, or `None` if no line has a pod prefix.
+    pub fn new<'a>(pods: impl Iterator<Item = &'a str>, hidden: &BTreeSet<String>) -> Option<Self> {
+        let mut listed: Vec<(String, usize, bool)> = vec![];
+        for name in pods {
+            match listed.iter_mut().find(|(listed, _, _)| listed == name) {
+                Some((_, count, _)) => *count += 1,
+                None => listed.push((name.to_string(), 1, !hidden.contains(name))),
+            }
+        }
+        (!listed.is_empty()).then_some(Self {
+            pods: listed,
+            cursor: 0,
+        })
+    }
+
+    pub fn up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.pods.len() - 1);
+    }
+
+    /// Shows or hides the lines of the pod at the cursor.
+    pub fn toggle(&mut self) {
+        if let Some((_, _, shown)) = self.pods.get_mut(self.cursor) {
+            *shown = !*shown;
+        }
+    }
+
+    /// Shows the lines of all the pods, or hides them all if all are shown.
+    pub fn toggle_all(&mut self) {
+        let shown = !self.pods.iter().all(|(_, _, shown)| *shown);
+        self.pods.iter_mut().for_each(|pod| pod.2 = shown);
+    }
+
+    /// Returns the pods whose lines are hidden.
+    pub fn hidden(&self) -> BTreeSet<String> {
+        self.pods
+            .iter()
+            .filter(|(_, _, shown)| !shown)
+            .map(|(name, _, _)| name.clone())
+            .collect()
+    }
+
+    pub fn create_pane(&self, width: u16, height: u16) -> Pane {
+        let dim = ContentStyle {
+            attributes: Attribute::Dim.into(),
+            ..Default::default()
+        };
+        let mut rows = vec![StyledGraphemes::from_str(
+            "Pods (space: show or hide, a: all, enter: apply, esc: cancel)",
+            dim,
+        )];
+        for (i, (name, count, shown)) in self.pods.iter().enumerate() {
+            let cursor = if i == self.cursor { "❯ " } else { "  " };
+            let check = if *shown { "[x] " } else { "[ ] " };
+            let row = StyledGraphemes::from_iter([
+                StyledGraphemes::from(format!("{}{}", cursor, check)),
+                StyledGraphemes::from_str(name, pod::style(name)),
+                StyledGraphemes::from_str(format!(" ({} lines)", count), dim),
+            ]);
+            rows.extend(row.matrixify(width as usize, height as usize, 0).0);
+        }
+        // Scrolled for the cursor, below the title, to stay in view.
+        let offset = (self.cursor + 2).saturating_sub(height as usize);
+        Pane::new(rows, offset)
+    }
+}
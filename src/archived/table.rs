@@ -0,0 +1,315 @@
+use promkit::{
+    crossterm::style::ContentStyle,
+    grapheme::{StyledGrapheme, StyledGraphemes},
+    serde_json::Value,
+};
+
+use crate::{json, logfmt, pod};
+
+/// Columns wider than this are cut with an ellipsis, except for the last one.
+const MAX_WIDTH: usize = 40;
+
+/// Gap between columns.
+const SEPARATOR: &str = "  ";
+
+/// How the lines are read as records.
+enum Source {
+    /// JSON objects or logfmt lines, each read by itself.
+    Lines,
+    /// CSV or TSV, with the names of the columns in the first line.
+    Delimited {
+        delimiter: char,
+        header: Vec<String>,
+    },
+}
+
+impl Source {
+    /// Takes the lines for CSV (or TSV) if the first one is a header
+    /// with as many columns as the line after it.
+    fn detect(lines: &[String]) -> Self {
+        let Some(first) = lines.first() else {
+            return Source::Lines;
+        };
+        let message = &first[pod::message_start(first)..];
+        if pod::parse(first).is_some()
+            || json::record(message).is_some()
+            || logfmt::parse(message).is_some()
+        {
+            return Source::Lines;
+        }
+        let delimiter = if first.contains('\t') { '\t' } else { ',' };
+        let header = split(first, delimiter);
+        match lines.get(1) {
+            Some(second) if header.len() > 1 && split(second, delimiter).len() == header.len() => {
+                Source::Delimited { delimiter, header }
+            }
+            _ => Source::Lines,
+        }
+    }
+
+    /// Returns the fields of the line by their names, or `None` if it is not a record.
+    /// The pod and container of a pod prefix come first, before those of the message.
+    fn fields(&self, index: usize, line: &str) -> Option<Vec<(String, String)>> {
+        match self {
+            Source::Lines => {
+                let prefix = pod::parse(line);
+                let start = prefix.as_ref().map_or(0, |prefix| prefix.end);
+                let mut fields = vec![];
+                if let Some(prefix) = &prefix {
+                    flatten("", &prefix.record(line), &mut fields);
+                }
+                if let Some(record) = json::record(&line[start..]) {
+                    flatten("", &record, &mut fields);
+                } else if let Some(parsed) = logfmt::parse_from(line, start) {
+                    fields.extend(
+                        parsed
+                            .iter()
+                            .map(|field| (line[field.key.clone()].to_string(), field.value(line))),
+                    );
+                } else if prefix.is_none() {
+                    return None;
+                }
+                Some(fields)
+            }
+            Source::Delimited { .. } if index == 0 => None,
+            Source::Delimited { delimiter, header } => Some(
+                header
+                    .iter()
+                    .cloned()
+                    .zip(split(line, *delimiter))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Collects the scalars of the JSON value keyed by their dotted paths, e.g. `http.status`.
+fn flatten(path: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if path.is_empty() || !map.is_empty() => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten(&path, value, fields);
+            }
+        }
+        Value::String(s) => fields.push((path.to_string(), s.clone())),
+        other => fields.push((path.to_string(), other.to_string())),
+    }
+}
+
+/// Splits a line of CSV (or TSV) into its fields, unquoting those in double quotes.
+fn split(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted || field.is_empty() => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Fields picked from the records, shown as aligned columns in place of the lines.
+pub struct Table {
+    pub columns: Vec<String>,
+    /// Cells of each line of the original queue, or `None` for lines that are not records.
+    cells: Vec<Option<Vec<String>>>,
+    widths: Vec<usize>,
+    /// Index of the line naming the columns of CSV (or TSV), which is not a row itself.
+    header_line: Option<usize>,
+}
+
+impl Table {
+    /// Returns the table of the columns, or `None` if none of the lines is a record.
+    pub fn new(lines: &[String], columns: Vec<String>) -> Option<Self> {
+        let source = Source::detect(lines);
+        let cells: Vec<Option<Vec<String>>> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                source.fields(index, line).map(|fields| {
+                    columns
+                        .iter()
+                        .map(|column| {
+                            fields
+                                .iter()
+                                .find(|(key, _)| key == column)
+                                .map(|(_, value)| value.replace(char::is_control, " "))
+                                .unwrap_or_default()
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+        if cells.iter().all(Option::is_none) {
+            return None;
+        }
+
+        let last = columns.len().saturating_sub(1);
+        let widths = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let width = cells
+                    .iter()
+                    .flatten()
+                    .map(|row| StyledGraphemes::from(&row[i]).widths())
+                    .chain([StyledGraphemes::from(column).widths()])
+                    .max()
+                    .unwrap_or(0);
+                if i == last {
+                    width
+                } else {
+                    width.min(MAX_WIDTH)
+                }
+            })
+            .collect();
+
+        Some(Self {
+            columns,
+            cells,
+            widths,
+            header_line: matches!(source, Source::Delimited { .. }).then_some(0),
+        })
+    }
+
+    /// Returns the names of the fields of the records, in the order they first appear.
+    pub fn fields(lines: &[String]) -> Vec<String> {
+        let source = Source::detect(lines);
+        let mut names: Vec<String> = vec![];
+        for (index, line) in lines.iter().enumerate() {
+            for (key, _) in source.fields(index, line).unwrap_or_default() {
+                if !names.contains(&key) {
+                    names.push(key);
+                }
+            }
+        }
+        names
+    }
+
+    pub fn is_header_line(&self, index: usize) -> bool {
+        self.header_line == Some(index)
+    }
+
+    /// Returns the cells of the line aligned in columns, or `None` if it is not a record.
+    pub fn row(&self, index: usize) -> Option<String> {
+        self.cells
+            .get(index)?
+            .as_ref()
+            .map(|cells| self.align(cells.iter().map(String::as_str)))
+    }
+
+    /// Returns the names of the columns aligned as the rows, in the style.
+    pub fn header(&self, style: ContentStyle) -> StyledGraphemes {
+        StyledGraphemes::from_str(self.align(self.columns.iter().map(String::as_str)), style)
+    }
+
+    fn align<'a>(&self, cells: impl Iterator<Item = &'a str>) -> String {
+        let last = self.widths.len().saturating_sub(1);
+        cells
+            .zip(&self.widths)
+            .enumerate()
+            .map(|(i, (cell, &width))| {
+                let mut cell = fit(cell, width);
+                if i < last {
+                    let padding = width - StyledGraphemes::from(&cell).widths();
+                    cell.push_str(&" ".repeat(padding));
+                }
+                cell
+            })
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    }
+}
+
+/// Cuts the text to the width, ending it with an ellipsis if anything is cut.
+fn fit(text: &str, width: usize) -> String {
+    if StyledGraphemes::from(text).widths() <= width {
+        return text.to_string();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = StyledGrapheme::new(c, ContentStyle::default()).width();
+        if used + w + 1 > width {
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    fitted.push('…');
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_quoted_fields() {
+        assert_eq!(split("a,b,,c", ','), ["a", "b", "", "c"]);
+        assert_eq!(
+            split(r#""GET /a, /b",200,"say ""hi""""#, ','),
+            ["GET /a, /b", "200", r#"say "hi""#]
+        );
+        assert_eq!(split("a\tb,c", '\t'), ["a", "b,c"]);
+        assert_eq!(split(r#"5"" screen,x"#, ','), [r#"5"" screen"#, "x"]);
+    }
+
+    #[test]
+    fn reads_records_by_their_dotted_paths() {
+        let lines = lines(&[
+            r#"{"level": "info", "http": {"status": 200}, "tags": []}"#,
+            "level=warn msg=\"slow down\"",
+            "plain text",
+        ]);
+        assert_eq!(
+            Table::fields(&lines),
+            ["level", "http.status", "tags", "msg"]
+        );
+        let table = Table::new(&lines, vec!["level".into(), "http.status".into()]).unwrap();
+        assert_eq!(table.row(0).unwrap(), "info   200");
+        assert_eq!(table.row(1).unwrap(), "warn   ");
+        assert_eq!(table.row(2), None);
+        assert!(!table.is_header_line(0));
+    }
+
+    #[test]
+    fn reads_csv_by_its_header() {
+        let lines = lines(&["time,status,path", "10:00,200,/a", "10:01,404,\"/b,c\""]);
+        assert_eq!(Table::fields(&lines), ["time", "status", "path"]);
+        let table = Table::new(&lines, vec!["path".into(), "status".into()]).unwrap();
+        assert!(table.is_header_line(0));
+        assert_eq!(table.row(0), None);
+        assert_eq!(table.row(2).unwrap(), "/b,c  404");
+    }
+
+    #[test]
+    fn needs_a_record() {
+        let lines = lines(&["starting", "ready"]);
+        assert!(Table::new(&lines, vec!["level".into()]).is_none());
+    }
+
+    #[test]
+    fn cuts_wide_cells() {
+        assert_eq!(fit("timeout", 10), "timeout");
+        assert_eq!(fit("timeout", 5), "time…");
+    }
+}
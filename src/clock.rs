@@ -0,0 +1,36 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, Utc};
+
+/// Returns the current time in the local time zone.
+pub fn now() -> DateTime<FixedOffset> {
+    let now = Utc::now();
+    now.with_timezone(&local_offset(now.timestamp()))
+}
+
+/// Returns the wall-clock time in the local time zone, e.g. of a timestamp without an offset.
+pub fn localize(time: NaiveDateTime) -> DateTime<FixedOffset> {
+    // The offset at the same wall-clock time in UTC is off only around a change of the offset.
+    let offset = local_offset(time.and_utc().timestamp());
+    time.and_local_timezone(offset)
+        .single()
+        .unwrap_or_else(|| time.and_utc().fixed_offset())
+}
+
+/// Returns the offset of the local time zone at the time,
+/// which chrono can only tell with the time zone database it is built without.
+#[cfg(unix)]
+pub fn local_offset(timestamp: i64) -> FixedOffset {
+    let time = timestamp as libc::time_t;
+    // SAFETY: `tm` is plain data, which `localtime_r` fills in.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let offset = if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        0
+    } else {
+        tm.tm_gmtoff as i32
+    };
+    FixedOffset::east_opt(offset).unwrap_or(Utc.fix())
+}
+
+#[cfg(not(unix))]
+pub fn local_offset(_timestamp: i64) -> FixedOffset {
+    Utc.fix()
+}
@@ -32,18 +32,29 @@ pub async fn execute(
     let mut stdout_reader = BufReader::new(stdout).lines();
     let mut stderr_reader = BufReader::new(stderr).lines();
 
-    while !canceled.is_cancelled() {
+    let mut stdout_closed = false;
+    let mut stderr_closed = false;
+
+    while !canceled.is_cancelled() && (!stdout_closed || !stderr_closed) {
         tokio::select! {
-            stdout_res = timeout(retrieval_timeout, stdout_reader.next_line()) => {
-                if let Ok(Ok(Some(line))) = stdout_res {
-                    let escaped = strip_ansi_escapes::strip_str(line.replace(['\n', '\t'], " "));
-                    tx.send(escaped).await?;
+            stdout_res = timeout(retrieval_timeout, stdout_reader.next_line()), if !stdout_closed => {
+                match stdout_res {
+                    Ok(Ok(Some(line))) => {
+                        let escaped = strip_ansi_escapes::strip_str(line.replace(['\n', '\t'], " "));
+                        tx.send(escaped).await?;
+                    }
+                    Ok(_) => stdout_closed = true,
+                    Err(_) => {}
                 }
             },
-            stderr_res = timeout(retrieval_timeout, stderr_reader.next_line()) => {
-                if let Ok(Ok(Some(line))) = stderr_res {
-                    let escaped = strip_ansi_escapes::strip_str(line.replace(['\n', '\t'], " "));
-                    tx.send(escaped).await?;
+            stderr_res = timeout(retrieval_timeout, stderr_reader.next_line()), if !stderr_closed => {
+                match stderr_res {
+                    Ok(Ok(Some(line))) => {
+                        let escaped = strip_ansi_escapes::strip_str(line.replace(['\n', '\t'], " "));
+                        tx.send(escaped).await?;
+                    }
+                    Ok(_) => stderr_closed = true,
+                    Err(_) => {}
                 }
             }
         }
@@ -0,0 +1,281 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::PathBuf,
+};
+
+use clap::builder::ArgPredicate;
+use serde::Deserialize;
+
+use promkit::{
+    crossterm::style::{Attribute, Attributes, Color, ContentStyle},
+    listbox, text_editor,
+};
+
+/// Contents of the configuration file.
+///
+/// ```toml
+/// [options]
+/// queue-capacity = 5000
+/// ignore-case = true
+///
+/// [theme]
+/// streaming-prefix = "> "
+/// highlight = { fg = "yellow", attributes = ["Bold"] }
+///
+/// [keybindings.archived]
+/// choose-filtered = ["ctrl+o", "alt+enter"]
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Defaults for the command line options, keyed by their long names.
+    pub options: toml::Table,
+    pub theme: Theme,
+    pub keybindings: KeybindingsConfig,
+}
+
+/// Chords of the actions to rebind in each mode, keyed by the action names.
+/// Listing an action replaces all of its chords in the preset, and takes the chords it lists
+/// from the actions of the preset bound to them.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeybindingsConfig {
+    pub streaming: HashMap<String, Vec<String>>,
+    pub archived: HashMap<String, Vec<String>>,
+}
+
+/// Prompts and styles of the interface.
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Theme {
+    pub streaming_prefix: String,
+    pub streaming_prefix_style: Style,
+    pub archived_prefix: String,
+    pub archived_prefix_style: Style,
+    /// Style of the character at the cursor of the query.
+    pub active_char: Style,
+    /// Style of the other characters of the query.
+    pub inactive_char: Style,
+    /// Style of the matches in lines.
+    pub highlight: Style,
+    /// Style of the line numbers and arrival times before lines.
+    pub gutter: Style,
+    /// Styles of the keys and values of logfmt lines.
+    pub field_key: Style,
+    pub field_value: Style,
+    /// Styles of the levels of lines, shown with `--levels`.
+    pub level_error: Style,
+    pub level_warn: Style,
+    pub level_info: Style,
+    pub level_debug: Style,
+    pub level_trace: Style,
+    /// Symbol for the selected line in archived mode.
+    pub cursor: String,
+    pub active_item: Option<Style>,
+    pub inactive_item: Option<Style>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            streaming_prefix: String::from("❯❯ "),
+            streaming_prefix_style: Style::fg(Color::DarkGreen),
+            archived_prefix: String::from("❯❯❯ "),
+            archived_prefix_style: Style::fg(Color::DarkBlue),
+            active_char: Style {
+                bg: Some(Color::DarkCyan),
+                ..Default::default()
+            },
+            inactive_char: Style::default(),
+            highlight: Style::fg(Color::Red),
+            gutter: Style::fg(Color::DarkGrey),
+            field_key: Style::fg(Color::DarkCyan),
+            field_value: Style::default(),
+            level_error: Style {
+                fg: Some(Color::DarkRed),
+                attributes: vec![Attribute::Bold],
+                ..Default::default()
+            },
+            level_warn: Style::fg(Color::DarkYellow),
+            level_info: Style::fg(Color::DarkGreen),
+            level_debug: Style::fg(Color::DarkBlue),
+            level_trace: Style::fg(Color::DarkGrey),
+            cursor: String::from("❯ "),
+            active_item: None,
+            inactive_item: None,
+        }
+    }
+}
+
+impl Theme {
+    pub fn streaming_text_editor(&self, query: &str) -> text_editor::State {
+        self.text_editor(query, &self.streaming_prefix, &self.streaming_prefix_style)
+    }
+
+    pub fn archived_text_editor(&self, query: &str) -> text_editor::State {
+        self.text_editor(query, &self.archived_prefix, &self.archived_prefix_style)
+    }
+
+    fn text_editor(&self, query: &str, prefix: &str, prefix_style: &Style) -> text_editor::State {
+        text_editor::State {
+            texteditor: text_editor::TextEditor::new(query),
+            history: Default::default(),
+            prefix: prefix.to_string(),
+            mask: Default::default(),
+            prefix_style: prefix_style.into(),
+            active_char_style: (&self.active_char).into(),
+            inactive_char_style: (&self.inactive_char).into(),
+            edit_mode: Default::default(),
+            word_break_chars: HashSet::from([' ', '|']),
+            lines: Default::default(),
+        }
+    }
+
+    pub fn listbox(&self, lines: impl IntoIterator<Item = String>) -> listbox::State {
+        listbox::State {
+            listbox: listbox::Listbox::from_displayable(lines),
+            cursor: self.cursor.clone(),
+            active_item_style: self.active_item.as_ref().map(Into::into),
+            inactive_item_style: self.inactive_item.as_ref().map(Into::into),
+            lines: Default::default(),
+        }
+    }
+}
+
+/// A style written as e.g. `{ fg = "red", bg = "#1e1e1e", attributes = ["Bold"] }`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Style {
+    fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&Style> for ContentStyle {
+    fn from(style: &Style) -> Self {
+        ContentStyle {
+            foreground_color: style.fg,
+            background_color: style.bg,
+            underline_color: None,
+            attributes: Attributes::from(style.attributes.as_slice()),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the file given by `--config`, or the default one if it exists.
+    pub fn load() -> anyhow::Result<Self> {
+        let (path, explicit) = match path_from_args() {
+            Some(path) => (path, true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("invalid config {}: {}", path.display(), e)),
+            Err(_) if !explicit => Ok(Self::default()),
+            Err(e) => Err(anyhow::anyhow!("failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Uses the options of the file as the defaults of the command line,
+    /// so that flags given explicitly still take precedence,
+    /// with `--no-<flag>` to turn off a flag set in the file
+    /// (except for flags already turning something off, e.g. `--no-tui`).
+    pub fn apply(&self, mut command: clap::Command) -> anyhow::Result<clap::Command> {
+        for (name, value) in &self.options {
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(name) && name != "config")
+                .ok_or_else(|| anyhow::anyhow!("unknown option in config: {}", name))?;
+            let value = match value {
+                // Options taking several values separated by a delimiter, e.g. `hide-keys`.
+                toml::Value::Array(items) => {
+                    let delimiter = arg.get_value_delimiter().ok_or_else(|| {
+                        anyhow::anyhow!("option in config takes a single value: {}", name)
+                    })?;
+                    items
+                        .iter()
+                        .map(|item| scalar(name, item))
+                        .collect::<anyhow::Result<Vec<_>>>()?
+                        .join(&delimiter.to_string())
+                }
+                other => scalar(name, other)?,
+            };
+            let id = arg.get_id().clone();
+            command = command.mut_arg(id, |arg| arg.default_value(value));
+        }
+
+        let flags: Vec<(clap::Id, String)> = command
+            .get_arguments()
+            .filter(|arg| matches!(arg.get_action(), clap::ArgAction::SetTrue))
+            .filter_map(|arg| Some((arg.get_id().clone(), arg.get_long()?.to_string())))
+            .filter(|(_, long)| !long.starts_with("no-"))
+            .collect();
+        for (id, long) in flags {
+            let negation = format!("no-{}", long);
+            command = command
+                .mut_arg(&id, |arg| {
+                    // Whichever of the two comes last on the command line wins.
+                    arg.default_value_if(&negation, ArgPredicate::IsPresent, Some("false"))
+                        .overrides_with(&negation)
+                })
+                .arg(
+                    clap::Arg::new(&negation)
+                        .long(&negation)
+                        .action(clap::ArgAction::SetTrue)
+                        .overrides_with(id)
+                        .hide(true),
+                );
+        }
+        Ok(command)
+    }
+}
+
+/// Returns the value of an option in the file as it would be given on the command line.
+fn scalar(name: &str, value: &toml::Value) -> anyhow::Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Array(_) | toml::Value::Table(_) => Err(anyhow::anyhow!(
+            "option in config must be a string, number or boolean: {}",
+            name
+        )),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Finds `--config <path>` ahead of parsing, since the file affects the parser itself.
+fn path_from_args() -> Option<PathBuf> {
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Returns `$XDG_CONFIG_HOME/sig/config.toml`, falling back to `~/.config/sig/config.toml`.
+fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("sig").join("config.toml"))
+}
@@ -0,0 +1,39 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use regex::Regex;
+
+/// How consecutive lines are told to be duplicates, to be collapsed into one.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Dedupe {
+    /// Lines exactly the same.
+    Exact,
+    /// Lines the same but for their numbers, timestamps and UUIDs.
+    Masked,
+}
+
+/// UUIDs, hexadecimal numbers and decimal ones,
+/// the last of which also mask the parts of timestamps.
+fn variables() -> &'static Regex {
+    static VARIABLES: OnceLock<Regex> = OnceLock::new();
+    VARIABLES.get_or_init(|| {
+        Regex::new(
+            r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}|0x[0-9a-fA-F]+|\d+(?:\.\d+)?",
+        )
+        .unwrap()
+    })
+}
+
+/// Returns the line with its UUIDs, numbers and timestamps replaced by `#`.
+fn mask(line: &str) -> Cow<'_, str> {
+    variables().replace_all(line, "#")
+}
+
+impl Dedupe {
+    /// Returns whether the line is a duplicate of the one before it.
+    pub fn same(&self, before: &str, line: &str) -> bool {
+        match self {
+            Dedupe::Exact => before == line,
+            Dedupe::Masked => before == line || mask(before) == mask(line),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use promkit::text_editor;
+
+/// Maximum number of changes which can be undone.
+const LIMIT: usize = 100;
+
+/// A query with the cursor position.
+struct Entry {
+    query: String,
+    position: usize,
+}
+
+/// Undo and redo of the changes to the query.
+/// Only the queries are kept, and what they filter is computed again when restored,
+/// as keeping the lines filtered by each would take up to as many times the memory.
+pub struct Undo {
+    done: Vec<Entry>,
+    undone: Vec<Entry>,
+    /// The query the changes lead to, telling new changes apart from undoing.
+    query: String,
+    /// Whether the last change was typing a character of a word,
+    /// so that typing a whole word is undone at once.
+    typing: bool,
+}
+
+impl Undo {
+    pub fn new(query: &str) -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            query: query.to_string(),
+            typing: false,
+        }
+    }
+
+    /// Returns whether the query has changed since it was last recorded or restored.
+    pub fn changed(&self, state: &text_editor::State) -> bool {
+        state.texteditor.text_without_cursor().to_string() != self.query
+    }
+
+    /// Records the change from the previous query, whose cursor was at `position`.
+    pub fn record(&mut self, state: &text_editor::State, position: usize) {
+        let query = state.texteditor.text_without_cursor().to_string();
+        let typing = typed(&self.query, position, &query, state.texteditor.position());
+        if !(typing && self.typing) {
+            self.done.push(Entry {
+                query: std::mem::replace(&mut self.query, query),
+                position,
+            });
+            if self.done.len() > LIMIT {
+                self.done.remove(0);
+            }
+        } else {
+            self.query = query;
+        }
+        self.undone.clear();
+        self.typing = typing;
+    }
+
+    /// Restores the query before the last change, returning whether there was one.
+    pub fn undo(&mut self, state: &mut text_editor::State) -> bool {
+        let Some(entry) = self.done.pop() else {
+            return false;
+        };
+        let current = self.restore(state, entry);
+        self.undone.push(current);
+        true
+    }
+
+    /// Restores the query undone last, returning whether there was one.
+    pub fn redo(&mut self, state: &mut text_editor::State) -> bool {
+        let Some(entry) = self.undone.pop() else {
+            return false;
+        };
+        let current = self.restore(state, entry);
+        self.done.push(current);
+        true
+    }
+
+    fn restore(
+        &mut self,
+        state: &mut text_editor::State,
+        Entry { query, position }: Entry,
+    ) -> Entry {
+        let current = Entry {
+            query: std::mem::replace(&mut self.query, query),
+            position: state.texteditor.position(),
+        };
+        state.texteditor.replace(&self.query);
+        for _ in position..self.query.chars().count() {
+            state.texteditor.backward();
+        }
+        self.typing = false;
+        current
+    }
+}
+
+/// Returns whether `after` is `before` with a word character typed at the cursor.
+fn typed(before: &str, position: usize, after: &str, new_position: usize) -> bool {
+    let before: Vec<char> = before.chars().collect();
+    let after: Vec<char> = after.chars().collect();
+    after.len() == before.len() + 1
+        && new_position == position + 1
+        && after[..position] == before[..position]
+        && after[new_position..] == before[position..]
+        && !after[position].is_whitespace()
+}
+
+/// Applies an edit which removes text, returning the text removed.
+pub fn kill(state: &mut text_editor::State, erase: impl FnOnce(&mut text_editor::State)) -> String {
+    let before: Vec<char> = state.texteditor.text_without_cursor().chars();
+    erase(state);
+    let after: Vec<char> = state.texteditor.text_without_cursor().chars();
+    let start = before
+        .iter()
+        .zip(&after)
+        .take_while(|(b, a)| b == a)
+        .count();
+    before[start..start + before.len() - after.len()]
+        .iter()
+        .collect()
+}
+
+/// Erases the text from the cursor to the end.
+pub fn erase_to_end(state: &mut text_editor::State) {
+    let count = state.texteditor.text_without_cursor().len() - state.texteditor.position();
+    state.texteditor.move_to_tail();
+    for _ in 0..count {
+        state.texteditor.erase();
+    }
+}
+
+/// Erases the character at the cursor.
+pub fn delete(state: &mut text_editor::State) {
+    if state.texteditor.forward() {
+        state.texteditor.erase();
+    }
+}
+
+/// Inserts the text at the cursor.
+pub fn yank(state: &mut text_editor::State, text: &str) {
+    for ch in text.chars() {
+        match state.edit_mode {
+            text_editor::Mode::Insert => state.texteditor.insert(ch),
+            text_editor::Mode::Overwrite => state.texteditor.overwrite(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use promkit::{crossterm::style::ContentStyle, text_editor::TextEditor};
+
+    use super::*;
+
+    fn state(text: &str) -> text_editor::State {
+        text_editor::State {
+            texteditor: TextEditor::new(text),
+            history: None,
+            prefix: String::new(),
+            mask: None,
+            prefix_style: ContentStyle::default(),
+            active_char_style: ContentStyle::default(),
+            inactive_char_style: ContentStyle::default(),
+            edit_mode: text_editor::Mode::Insert,
+            word_break_chars: HashSet::new(),
+            lines: None,
+        }
+    }
+
+    fn text(state: &text_editor::State) -> String {
+        state.texteditor.text_without_cursor().to_string()
+    }
+
+    fn type_text(state: &mut text_editor::State, undo: &mut Undo, text: &str) {
+        for ch in text.chars() {
+            let position = state.texteditor.position();
+            state.texteditor.insert(ch);
+            undo.record(state, position);
+        }
+    }
+
+    #[test]
+    fn undoes_typing_a_word_at_once() {
+        let mut state = state("");
+        let mut undo = Undo::new("");
+        type_text(&mut state, &mut undo, "ab c");
+        assert!(undo.undo(&mut state));
+        assert_eq!(text(&state), "ab ");
+        assert!(undo.undo(&mut state));
+        assert_eq!(text(&state), "ab");
+        assert_eq!(state.texteditor.position(), 2);
+        assert!(undo.undo(&mut state));
+        assert_eq!(text(&state), "");
+        assert!(!undo.undo(&mut state));
+        assert!(!undo.changed(&state));
+    }
+
+    #[test]
+    fn redoes_until_a_new_change() {
+        let mut state = state("");
+        let mut undo = Undo::new("");
+        type_text(&mut state, &mut undo, "ab c");
+        undo.undo(&mut state);
+        undo.undo(&mut state);
+        assert!(undo.redo(&mut state));
+        assert_eq!(text(&state), "ab ");
+        assert!(undo.redo(&mut state));
+        assert_eq!(text(&state), "ab c");
+        assert!(!undo.redo(&mut state));
+
+        undo.undo(&mut state);
+        type_text(&mut state, &mut undo, "d");
+        assert_eq!(text(&state), "ab d");
+        assert!(!undo.redo(&mut state));
+    }
+
+    #[test]
+    fn keeps_up_to_the_limit_of_changes() {
+        let mut state = state("");
+        let mut undo = Undo::new("");
+        for i in 0..LIMIT + 10 {
+            let position = state.texteditor.position();
+            state.texteditor.replace(&format!("q{} ", i));
+            assert!(undo.changed(&state));
+            undo.record(&state, position);
+        }
+        let mut count = 0;
+        while undo.undo(&mut state) {
+            count += 1;
+        }
+        assert_eq!(count, LIMIT);
+        assert_eq!(text(&state), "q9 ");
+    }
+
+    #[test]
+    fn kills_and_yanks_text() {
+        let mut state = state("error timeout");
+        state.texteditor.move_to_head();
+        for _ in 0.."error".len() {
+            state.texteditor.forward();
+        }
+        assert_eq!(kill(&mut state, erase_to_end), " timeout");
+        assert_eq!(text(&state), "error");
+        yank(&mut state, "|warn");
+        assert_eq!(text(&state), "error|warn");
+        state.texteditor.move_to_head();
+        delete(&mut state);
+        assert_eq!(text(&state), "rror|warn");
+    }
+}
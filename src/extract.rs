@@ -0,0 +1,115 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::sig;
+
+/// Picks the text captured by the groups of the query out of the lines it matches,
+/// like `rg -o -r '$1'`.
+pub struct Extractor {
+    pub query: String,
+    case_insensitive: bool,
+    /// Alternatives of the query with capture groups, as regular expressions.
+    regexes: Vec<Regex>,
+}
+
+impl Extractor {
+    /// Returns the extractor of the query, or `None` if none of its alternatives
+    /// (e.g. `status=(\d+)`) has a capture group.
+    pub fn new(query: &str, case_insensitive: bool) -> Option<Self> {
+        let regexes: Vec<Regex> = query
+            .split('|')
+            .map(str::trim)
+            .filter(|alternative| !alternative.is_empty())
+            .filter_map(|alternative| {
+                RegexBuilder::new(alternative)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .ok()
+            })
+            .filter(|regex| regex.captures_len() > 1)
+            .collect();
+        (!regexes.is_empty()).then(|| Self {
+            query: query.to_string(),
+            case_insensitive,
+            regexes,
+        })
+    }
+
+    /// Returns what each match in the line captures, its groups separated by spaces,
+    /// or `None` if the query filters the line out, so that the two agree.
+    pub fn captures(&self, line: &str) -> Option<Vec<String>> {
+        sig::find(&self.query, line, self.case_insensitive)?;
+        let captures = self
+            .regexes
+            .iter()
+            .flat_map(|regex| regex.captures_iter(line))
+            .map(|captures| {
+                captures
+                    .iter()
+                    .skip(1)
+                    .flatten()
+                    .map(|group| group.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|captured| !captured.is_empty())
+            .collect();
+        Some(captures)
+    }
+
+    /// Returns all that the line captures, or `None` if nothing
+    /// (or the query filters the line out).
+    pub fn extract(&self, line: &str) -> Option<String> {
+        let captures = self.captures(line)?;
+        (!captures.is_empty()).then(|| captures.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_a_capture_group() {
+        assert!(Extractor::new("status", false).is_none());
+        assert!(Extractor::new("(unclosed", false).is_none());
+        assert!(Extractor::new(r"status (\d+)", false).is_some());
+    }
+
+    #[test]
+    fn extracts_the_groups_of_each_match() {
+        let extractor = Extractor::new(r"(\w+)=(\d+)", false).unwrap();
+        assert_eq!(
+            extractor.captures("a=1 b=2 c=x").unwrap(),
+            ["a 1", "b 2"].map(String::from)
+        );
+        assert_eq!(extractor.extract("a=1 b=2").as_deref(), Some("a 1 b 2"));
+    }
+
+    #[test]
+    fn extracts_from_alternatives_with_groups() {
+        let extractor = Extractor::new(r"user=(\w+) | failed", false).unwrap();
+        assert_eq!(
+            extractor.extract("login user=alice").as_deref(),
+            Some("alice")
+        );
+        // Matching an alternative without groups, the line captures nothing.
+        assert_eq!(
+            extractor.captures("login failed").unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(extractor.extract("login failed"), None);
+    }
+
+    #[test]
+    fn agrees_with_the_query_on_the_lines_it_filters() {
+        let extractor = Extractor::new(r"ID=(\d+)", true).unwrap();
+        assert_eq!(extractor.extract("id=42").as_deref(), Some("42"));
+        assert_eq!(extractor.captures("nothing"), None);
+        assert_eq!(
+            Extractor::new(r"ID=(\d+)", false)
+                .unwrap()
+                .captures("id=42"),
+            None
+        );
+    }
+}
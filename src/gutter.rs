@@ -0,0 +1,105 @@
+use std::ops::Range;
+
+use chrono::{DateTime, FixedOffset};
+
+use promkit::{crossterm::style::ContentStyle, grapheme::StyledGraphemes};
+
+use crate::{
+    clock,
+    level::{self, Level},
+    timestamp,
+};
+
+/// A line of the stream, with where and when it arrived.
+#[derive(Clone)]
+pub struct Line {
+    /// Position in the whole stream, counting from 1.
+    pub number: usize,
+    pub arrived: DateTime<FixedOffset>,
+    pub text: String,
+    /// How many consecutive duplicates the line stands for, itself included.
+    pub repeats: usize,
+    /// Level of the line and where it is written, detected once as it arrives
+    /// rather than each time the line is filtered or drawn.
+    pub level: Option<(Level, Range<usize>)>,
+}
+
+impl Line {
+    /// Returns the line arriving now as the `number`-th of the stream.
+    pub fn new(number: usize, text: String) -> Self {
+        Self {
+            number,
+            arrived: clock::now(),
+            level: level::detect(&text),
+            text,
+            repeats: 1,
+        }
+    }
+
+    /// Returns the level of the line, if any was detected.
+    pub fn level(&self) -> Option<Level> {
+        self.level.as_ref().map(|(level, _)| *level)
+    }
+
+    /// Counts the duplicate arriving after the line on it, taking its text,
+    /// position and time as the latest of the lines.
+    pub fn repeat(&mut self, duplicate: Line) {
+        *self = Line {
+            repeats: self.repeats + 1,
+            ..duplicate
+        };
+    }
+}
+
+/// Columns shown before each line.
+#[derive(Clone, Default)]
+pub struct Gutter {
+    /// Whether to show the position of the line in the stream.
+    pub number: bool,
+    /// Whether to show the time the line arrived.
+    pub time: bool,
+    /// Parser of the timestamps of lines, to show how long ago they were (or arrived) if set.
+    pub relative: Option<timestamp::Parser>,
+    /// Whether to show how many duplicates each line stands for, e.g. `×12`.
+    pub repeats: bool,
+    pub style: ContentStyle,
+}
+
+impl Gutter {
+    /// Returns the gutter of the line, e.g. `   123 12:34:56.789  12s ago   ×12 `.
+    pub fn render(&self, line: &Line) -> StyledGraphemes {
+        let mut gutter = String::new();
+        if self.number {
+            gutter.push_str(&format!("{:>6} ", line.number));
+        }
+        if self.time {
+            gutter.push_str(&format!("{} ", line.arrived.format("%H:%M:%S%.3f")));
+        }
+        if let Some(parser) = &self.relative {
+            let time = parser.parse(&line.text).unwrap_or(line.arrived);
+            gutter.push_str(&format!("{:>8} ", timestamp::relative(time, clock::now())));
+        }
+        if self.repeats {
+            let repeats = if line.repeats > 1 {
+                format!("×{}", line.repeats)
+            } else {
+                String::new()
+            };
+            gutter.push_str(&format!("{:>5} ", repeats));
+        }
+        StyledGraphemes::from_str(gutter, self.style)
+    }
+}
+
+/// Puts the gutter before the first of the rows of a line,
+/// and blanks of the same width before the others.
+pub fn prefix(gutter: &StyledGraphemes, rows: Vec<StyledGraphemes>) -> Vec<StyledGraphemes> {
+    let blank = StyledGraphemes::from(" ".repeat(gutter.widths()));
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let gutter = if i == 0 { gutter } else { &blank };
+            StyledGraphemes::from_iter([gutter, &row])
+        })
+        .collect()
+}
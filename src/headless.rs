@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use promkit::crossterm::style::ContentStyle;
+use tokio::{sync::mpsc, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::{cmd, extract::Extractor, output::Format, sig, stdin};
+
+/// Writes the lines matching the query (or only what they capture) to stdout
+/// until the stream ends, without the interactive interface.
+pub async fn run(
+    query: &str,
+    format: Format,
+    highlight_style: ContentStyle,
+    retrieval_timeout: Duration,
+    case_insensitive: bool,
+    only_captures: bool,
+    cmd: Option<String>,
+) -> anyhow::Result<()> {
+    let (tx, mut rx) = mpsc::channel(1);
+
+    let streaming = if let Some(cmd) = cmd {
+        tokio::spawn(async move {
+            cmd::execute(&cmd, tx, retrieval_timeout, CancellationToken::new()).await
+        })
+    } else {
+        tokio::spawn(async move {
+            stdin::streaming(tx, retrieval_timeout, CancellationToken::new()).await
+        })
+    };
+
+    let extractor = only_captures
+        .then(|| Extractor::new(query, case_insensitive))
+        .flatten();
+    let mut stdout = io::stdout().lock();
+    while let Some(line) = rx.recv().await {
+        let rendered = match &extractor {
+            Some(extractor) => extractor
+                .extract(&line)
+                .map(|captured| format.render(&captured, &[], highlight_style)),
+            None => sig::find(query, &line, case_insensitive)
+                .map(|matches| format.render(&line, &matches, highlight_style)),
+        };
+        if let Some(rendered) = rendered {
+            match writeln!(stdout, "{}", rendered) {
+                // The reader (e.g. `head`) has gone away, so there is nothing left to do.
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                ret => ret?,
+            }
+        }
+    }
+    stdout.flush()?;
+
+    streaming.await?
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+
+use clap::ValueEnum;
+
+use promkit::{
+    crossterm::style::{Attribute, Attributes, ContentStyle},
+    grapheme::StyledGraphemes,
+    pane::Pane,
+    PaneFactory,
+};
+
+use crate::{
+    dedupe::Dedupe,
+    keybinding::{self, KeyChord, Keybindings},
+    level::Levels,
+    timestamp,
+};
+
+/// Settings of a mode in effect, to list in the help.
+pub struct Settings<'a> {
+    pub case_insensitive: bool,
+    pub levels: &'a Levels,
+    /// Whether long lines are truncated rather than wrapped,
+    /// or `None` in archived mode, which always wraps them.
+    pub truncate: Option<bool>,
+    pub only_captures: bool,
+    pub stats: bool,
+    pub dedupe: Option<Dedupe>,
+    /// Pods whose lines are filtered out, or `None` in streaming mode, which cannot.
+    pub hidden_pods: Option<&'a BTreeSet<String>>,
+    /// Filter of the lines by their timestamps if any,
+    /// or `None` in streaming mode, which cannot filter by them.
+    pub time_range: Option<Option<&'a timestamp::Range>>,
+    /// Whether the query is edited in vi-style normal mode.
+    pub normal: bool,
+}
+
+/// Overlay listing the key bindings of a mode and the current settings.
+#[derive(Clone)]
+pub struct Help {
+    /// Lines with whether each is a heading.
+    lines: Vec<(String, bool)>,
+    offset: usize,
+}
+
+impl Help {
+    pub fn new(mode: &str) -> Self {
+        Self {
+            lines: vec![(
+                format!(
+                    "Keys in {} mode (↑/↓ to scroll, any other key to close)",
+                    mode
+                ),
+                true,
+            )],
+            offset: 0,
+        }
+    }
+
+    /// Adds the bindings, one row per action in the order they were bound.
+    pub fn keybindings<A: ValueEnum + Copy + PartialEq>(
+        self,
+        heading: &str,
+        keybindings: &Keybindings<A>,
+    ) -> Self {
+        let mut actions: Vec<(A, Vec<KeyChord>)> = Vec::new();
+        for (chord, action) in keybindings.iter() {
+            match actions.iter_mut().find(|(bound, _)| bound == action) {
+                Some((_, chords)) => chords.push(*chord),
+                None => actions.push((*action, vec![*chord])),
+            }
+        }
+        let rows = actions.into_iter().map(|(action, chords)| {
+            let chords = chords
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let help = action
+                .to_possible_value()
+                .and_then(|v| v.get_help().map(ToString::to_string))
+                .unwrap_or_else(|| keybinding::name(&action));
+            (chords, help)
+        });
+        self.section(heading, rows)
+    }
+
+    /// Adds a section of two columns, e.g. keys and what they do.
+    pub fn section<I, K, V>(mut self, heading: &str, rows: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: ToString,
+        V: ToString,
+    {
+        self.lines.push((String::new(), false));
+        self.lines.push((heading.to_string(), true));
+
+        let rows: Vec<(String, String)> = rows
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let width = rows
+            .iter()
+            .map(|(k, _)| k.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.lines.extend(rows.into_iter().map(|(k, v)| {
+            (
+                format!("  {}{}  {}", k, " ".repeat(width - k.chars().count()), v),
+                false,
+            )
+        }));
+        self
+    }
+
+    /// Adds the settings in effect.
+    pub fn settings(self, settings: Settings) -> Self {
+        let on = |on: bool| String::from(if on { "on" } else { "off" });
+        let mut rows = vec![
+            ("ignore case", on(settings.case_insensitive)),
+            ("minimum level", settings.levels.describe_minimum()),
+        ];
+        if let Some(truncate) = settings.truncate {
+            rows.push((
+                "long lines",
+                String::from(if truncate { "truncated" } else { "wrapped" }),
+            ));
+        }
+        rows.extend([
+            ("only captures", on(settings.only_captures)),
+            ("stats", on(settings.stats)),
+            (
+                "dedupe",
+                settings
+                    .dedupe
+                    .and_then(|dedupe| dedupe.to_possible_value())
+                    .map_or_else(|| String::from("off"), |v| v.get_name().to_string()),
+            ),
+        ]);
+        if let Some(hidden_pods) = settings.hidden_pods {
+            rows.push((
+                "hidden pods",
+                if hidden_pods.is_empty() {
+                    String::from("none")
+                } else {
+                    hidden_pods.iter().cloned().collect::<Vec<_>>().join(", ")
+                },
+            ));
+        }
+        if let Some(time_range) = settings.time_range {
+            rows.push((
+                "time filter",
+                time_range.map_or_else(|| String::from("none"), |range| range.text.clone()),
+            ));
+        }
+        rows.extend([
+            (
+                "vi mode",
+                String::from(if settings.normal { "normal" } else { "insert" }),
+            ),
+            (
+                "query",
+                String::from("regular expressions, alternatives separated by `|`"),
+            ),
+            (
+                "",
+                String::from(
+                    "or conditions on JSON or logfmt fields, e.g. `level=error`, `.http.status>=500`",
+                ),
+            ),
+        ]);
+        self.section("Settings", rows)
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.offset = self.offset.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.offset + 1 < self.lines.len() {
+            self.offset += 1;
+        }
+    }
+}
+
+impl PaneFactory for Help {
+    fn create_pane(&self, width: u16, height: u16) -> Pane {
+        let heading = ContentStyle {
+            attributes: Attributes::from(Attribute::Bold),
+            ..Default::default()
+        };
+        // Lines are cut at the width rather than wrapped, to keep the columns aligned.
+        let rows = self.lines[self.offset..]
+            .iter()
+            .take(height as usize)
+            .map(|(line, is_heading)| {
+                let style = if *is_heading {
+                    heading
+                } else {
+                    ContentStyle::default()
+                };
+                StyledGraphemes::from_str(line, style)
+                    .matrixify(width as usize, 1, 0)
+                    .0
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default()
+            })
+            .collect();
+        Pane::new(rows, 0)
+    }
+}
@@ -0,0 +1,149 @@
+use std::{env, fs, path::PathBuf};
+
+use promkit::text_editor;
+
+/// Maximum number of queries kept in the history file.
+const LIMIT: usize = 1000;
+
+/// Returns `$XDG_STATE_HOME/sig/history`, falling back to `~/.local/state/sig/history`.
+fn path() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("sig").join("history"))
+}
+
+/// Loads the queries committed in previous sessions.
+pub fn load() -> text_editor::History {
+    let mut history = text_editor::History::default();
+    history.limit_size = Some(LIMIT);
+    if let Some(contents) = path().and_then(|path| fs::read_to_string(path).ok()) {
+        // The file is written newest first.
+        for line in contents.lines().rev().filter(|line| !line.is_empty()) {
+            history.insert(line);
+        }
+    }
+    history
+}
+
+/// Records the current query to the history and persists it.
+/// History is a convenience, so failing to write it is silently ignored.
+pub fn commit(state: &mut text_editor::State) {
+    let query = state.texteditor.text_without_cursor().to_string();
+    if let Some(history) = state.history.as_mut() {
+        if !query.is_empty() {
+            history.insert(&query);
+        }
+        if let Some(path) = path() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+            history.save_to_file(path).ok();
+        }
+    }
+}
+
+/// Replaces the query with the previous entry of the history.
+pub fn backward(state: &mut text_editor::State) {
+    if let Some(history) = state.history.as_mut() {
+        if history.backward() {
+            state.texteditor.replace(&history.get());
+        }
+    }
+}
+
+/// Replaces the query with the next entry of the history.
+pub fn forward(state: &mut text_editor::State) {
+    if let Some(history) = state.history.as_mut() {
+        if history.forward() {
+            state.texteditor.replace(&history.get());
+        }
+    }
+}
+
+/// Incremental search through the history, from the newest entry to the oldest.
+/// The pattern is shown in the prefix and the matching entry replaces the query.
+pub struct Search {
+    pattern: String,
+    prefix: String,
+    query: String,
+}
+
+impl Search {
+    pub fn start(state: &mut text_editor::State) -> Self {
+        if let Some(history) = state.history.as_mut() {
+            history.move_to_tail();
+        }
+        let ret = Self {
+            pattern: String::new(),
+            prefix: state.prefix.clone(),
+            query: state.texteditor.text_without_cursor().to_string(),
+        };
+        ret.update_prefix(state, true);
+        ret
+    }
+
+    pub fn push(&mut self, state: &mut text_editor::State, ch: char) {
+        self.pattern.push(ch);
+        let current = state
+            .history
+            .as_ref()
+            .map(|history| history.get())
+            .unwrap_or_default();
+        if current.contains(&self.pattern) {
+            self.update_prefix(state, true);
+        } else {
+            self.older(state);
+        }
+    }
+
+    pub fn pop(&mut self, state: &mut text_editor::State) {
+        self.pattern.pop();
+        if let Some(history) = state.history.as_mut() {
+            history.move_to_tail();
+        }
+        self.older(state);
+    }
+
+    /// Moves to the next older entry containing the pattern.
+    pub fn older(&mut self, state: &mut text_editor::State) {
+        let mut found = false;
+        if let Some(history) = state.history.as_mut() {
+            let mut probe = history.clone();
+            while probe.backward() {
+                let entry = probe.get();
+                if entry.contains(&self.pattern) {
+                    *history = probe;
+                    state.texteditor.replace(&entry);
+                    found = true;
+                    break;
+                }
+            }
+        }
+        self.update_prefix(state, found);
+    }
+
+    /// Keeps the found entry as the query.
+    pub fn accept(self, state: &mut text_editor::State) {
+        state.prefix = self.prefix;
+    }
+
+    /// Restores the query from before the search.
+    pub fn cancel(self, state: &mut text_editor::State) {
+        state.prefix = self.prefix;
+        state.texteditor.replace(&self.query);
+        if let Some(history) = state.history.as_mut() {
+            history.move_to_tail();
+        }
+    }
+
+    fn update_prefix(&self, state: &mut text_editor::State, found: bool) {
+        state.prefix = format!(
+            "({}history `{}`) {}",
+            if found { "" } else { "failing " },
+            self.pattern,
+            self.prefix
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::cmp::Ordering;
+
+use grep::matcher::Match;
+use promkit::serde_json::{self, Value};
+use regex::RegexBuilder;
+
+/// How a field is compared with the value of a condition.
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// The field matches the value as a regular expression.
+    Regex,
+}
+
+/// Characters which make a value a regular expression rather than a value to compare with,
+/// e.g. in `status=(\d+)`, leaving out `.` which is common in values such as versions or hosts.
+const REGEX_SYNTAX: &[char] = &['\\', '+', '*', '?', '(', ')', '[', ']', '{', '}', '^', '$'];
+
+/// A condition on a field of JSON (or logfmt) records, e.g. `level=error` or `.http.status>=500`.
+pub struct Condition {
+    /// Keys (or array indices) leading to the field.
+    path: Vec<String>,
+    op: Op,
+    value: String,
+}
+
+impl Condition {
+    /// Parses the condition, or returns `None` if the text is not one (but e.g. a regex).
+    /// A comparison with a value looking like a regular expression is taken as one,
+    /// unless the value is quoted.
+    pub fn parse(s: &str) -> Option<Self> {
+        let end = s
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '@')))
+            .unwrap_or(s.len());
+        let (path, rest) = s.split_at(end);
+        let path = path.strip_prefix('.').unwrap_or(path);
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            return None;
+        }
+
+        let (op, value) = [
+            ("!=", Op::Ne),
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            ("=", Op::Eq),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+            ("~", Op::Regex),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| rest.trim_start().strip_prefix(token).map(|v| (op, v)))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => quoted,
+            None if op != Op::Regex && value.contains(REGEX_SYNTAX) => return None,
+            None => value,
+        };
+
+        Some(Self {
+            path: path.split('.').map(ToString::to_string).collect(),
+            op,
+            value: value.to_string(),
+        })
+    }
+
+    /// Returns whether the record satisfies the condition,
+    /// or `None` if it has no such field, for the condition to be searched for as it is.
+    pub fn test(&self, record: &Value, case_insensitive: bool) -> Option<bool> {
+        let field = self
+            .path
+            .iter()
+            .try_fold(record, |value, key| match value {
+                Value::Object(map) => map.get(key),
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            })
+            // Keys may contain dots themselves, e.g. in logfmt.
+            .or_else(|| record.get(self.key()))?;
+
+        let text = match field {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        if self.op == Op::Regex {
+            return Some(
+                RegexBuilder::new(&self.value)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .is_ok_and(|re| re.is_match(&text)),
+            );
+        }
+
+        // Numbers are compared as such, even if written as strings, and anything else as text.
+        let number = field.as_f64().or_else(|| text.parse().ok());
+        let ordering = match (number, self.value.parse::<f64>()) {
+            (Some(a), Ok(b)) => a.partial_cmp(&b),
+            _ if case_insensitive => Some(text.to_lowercase().cmp(&self.value.to_lowercase())),
+            _ => Some(text.as_str().cmp(self.value.as_str())),
+        };
+        Some(ordering.is_some_and(|ordering| match self.op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Regex => unreachable!(),
+        }))
+    }
+
+    /// Returns the path to the field as written, without a leading dot.
+    pub fn key(&self) -> String {
+        self.path.join(".")
+    }
+
+    /// Returns where the field is in the line, from its key to the end of a scalar value.
+    /// Only the last key of the path is looked for, so this is a best guess for nested fields.
+    pub fn locate(&self, line: &str) -> Option<Match> {
+        let key = format!("\"{}\"", self.path.last()?);
+        let mut from = 0;
+        while let Some(found) = line[from..].find(&key) {
+            let start = from + found;
+            let after_key = start + key.len();
+            let rest = &line[after_key..];
+            let trimmed = rest.trim_start();
+            if let Some(value) = trimmed.strip_prefix(':') {
+                let value_start = line.len() - value.trim_start().len();
+                return Some(Match::new(
+                    start,
+                    value_start + scalar_len(&line[value_start..]),
+                ));
+            }
+            from = after_key;
+        }
+        None
+    }
+}
+
+/// Returns the length of the JSON string, number, boolean or null at the start of the text,
+/// or 0 for an object or array.
+fn scalar_len(text: &str) -> usize {
+    if let Some(body) = text.strip_prefix('"') {
+        let mut escaped = false;
+        for (i, c) in body.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => return i + 2,
+                _ => (),
+            }
+        }
+        return text.len();
+    }
+    if text.starts_with(['{', '[']) {
+        return 0;
+    }
+    text.find([',', '}', ']', ' ', '\t']).unwrap_or(text.len())
+}
+
+/// Returns the line parsed if it is a JSON object.
+pub fn record(line: &str) -> Option<Value> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str::<Value>(line)
+        .ok()
+        .filter(Value::is_object)
+}
+
+/// Returns the line pretty-printed if it is a JSON object.
+pub fn pretty(line: &str) -> Option<String> {
+    record(line).and_then(|record| serde_json::to_string_pretty(&record).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(condition: &str, line: &str) -> Option<bool> {
+        Condition::parse(condition)?.test(&record(line)?, false)
+    }
+
+    #[test]
+    fn parses_paths_and_operators() {
+        let condition = Condition::parse(".http.status>=500").unwrap();
+        assert_eq!(condition.key(), "http.status");
+        assert!(condition.op == Op::Ge);
+        assert_eq!(condition.value, "500");
+
+        assert!(Condition::parse("level = error").is_some());
+        assert!(Condition::parse("error").is_none());
+        assert!(Condition::parse(".=1").is_none());
+        assert!(Condition::parse("a..b=1").is_none());
+    }
+
+    #[test]
+    fn takes_values_looking_like_regexes_as_regexes() {
+        assert!(Condition::parse(r"status=(\d+)").is_none());
+        assert!(Condition::parse("msg=a*b").is_none());
+        // Unless quoted or compared with `~`.
+        assert_eq!(Condition::parse(r#"msg="a*b""#).unwrap().value, "a*b");
+        assert!(Condition::parse(r"msg~^time.*out$").is_some());
+        // Dots are common in values.
+        assert!(Condition::parse("version=1.2.3").is_some());
+    }
+
+    #[test]
+    fn compares_numbers_as_numbers() {
+        let line = r#"{"status": 503, "code": "42"}"#;
+        assert_eq!(test("status>=500", line), Some(true));
+        assert_eq!(test("status<60", line), Some(false));
+        assert_eq!(test("code>9", line), Some(true));
+        assert_eq!(test("code!=42", line), Some(false));
+    }
+
+    #[test]
+    fn compares_text_and_regexes() {
+        let line = r#"{"level": "ERROR", "msg": "timed out", "tags": ["a", "b"]}"#;
+        assert_eq!(test("level=ERROR", line), Some(true));
+        assert_eq!(test("level=error", line), Some(false));
+        let condition = Condition::parse("level=error").unwrap();
+        assert_eq!(condition.test(&record(line).unwrap(), true), Some(true));
+        assert_eq!(test("msg~out$", line), Some(true));
+        assert_eq!(test("tags.1=b", line), Some(true));
+    }
+
+    #[test]
+    fn tells_missing_fields_apart() {
+        let line = r#"{"level": "info", "http": {"status": 200}, "a.b": 1}"#;
+        assert_eq!(test("user=alice", line), None);
+        assert_eq!(test("http.method=GET", line), None);
+        assert_eq!(test("http.status.code=200", line), None);
+        // Keys with dots of their own are found as written.
+        assert_eq!(test("a.b=1", line), Some(true));
+    }
+
+    #[test]
+    fn locates_fields_in_lines() {
+        let line = r#"{"msg": "a \"quoted\" text", "status": 500, "nested": {"x": 1}}"#;
+        let found = Condition::parse("msg=x").unwrap().locate(line).unwrap();
+        assert_eq!(
+            &line[found.start()..found.end()],
+            r#""msg": "a \"quoted\" text""#
+        );
+        let found = Condition::parse("status=1").unwrap().locate(line).unwrap();
+        assert_eq!(&line[found.start()..found.end()], r#""status": 500"#);
+        let found = Condition::parse("nested=1").unwrap().locate(line).unwrap();
+        assert_eq!(&line[found.start()..found.end()], r#""nested": "#);
+        assert!(Condition::parse("user=x").unwrap().locate(line).is_none());
+    }
+
+    #[test]
+    fn reads_only_objects_as_records() {
+        assert!(record(r#"{"a": 1}"#).is_some());
+        assert!(record("[1, 2]").is_none());
+        assert!(record("{not json").is_none());
+        assert!(record("plain text").is_none());
+    }
+}
@@ -0,0 +1,416 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use clap::ValueEnum;
+
+use promkit::crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers,
+};
+
+/// Built-in sets of key bindings.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Emacs-like bindings (e.g. ctrl+a and ctrl+e to move to the start and end).
+    #[default]
+    Emacs,
+    /// Vi-like bindings (e.g. home and end to move to the start and end).
+    Vi,
+}
+
+/// A key together with its modifiers, written as e.g. `ctrl+r`, `alt+b` or `shift+tab`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub const fn key(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub const fn ctrl(ch: char) -> Self {
+        Self {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    pub const fn alt(ch: char) -> Self {
+        Self {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::ALT,
+        }
+    }
+
+    pub const fn shift(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::SHIFT,
+        }
+    }
+
+    /// Returns whether the chord types a character, such as `space` or `j`, rather than
+    /// being a key (e.g. `tab`) or having ctrl or alt held.
+    fn is_printable(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// Returns the chord of a key press, if the event is one.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            }) => Some(Self::normalized(*code, *modifiers)),
+            _ => None,
+        }
+    }
+
+    /// Shift is already reflected in characters (e.g. `?`) and in back-tab.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(_) | KeyCode::BackTab => Self {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("invalid key: {}", s);
+
+        // Split on `+` while still allowing `+` itself as the key (e.g. `ctrl++`).
+        let (mods, key) = match s.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest, "+"),
+            _ => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in mods.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            lower => match (lower.strip_prefix('f'), key.chars().count()) {
+                (Some(n), _) if !n.is_empty() => KeyCode::F(n.parse().map_err(|_| invalid())?),
+                (_, 1) => {
+                    let ch = key.chars().next().unwrap_or_default();
+                    if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+                        KeyCode::Char(ch.to_ascii_lowercase())
+                    } else {
+                        KeyCode::Char(ch)
+                    }
+                }
+                _ => return Err(invalid()),
+            },
+        };
+
+        Ok(Self::normalized(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// Key chords mapped to the named actions of a mode.
+#[derive(Clone)]
+pub struct Keybindings<A> {
+    bindings: Vec<(KeyChord, A)>,
+}
+
+impl<A: ValueEnum + Copy + PartialEq> Keybindings<A> {
+    pub fn new<I: IntoIterator<Item = (KeyChord, A)>>(bindings: I) -> Self {
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+
+    /// Rebinds the actions given by name to the given chords, which also take the chords
+    /// from whichever actions the preset binds them to, failing if a chord is given
+    /// to more than one action or types a character, which would no longer be typed.
+    pub fn configure(mut self, overrides: &HashMap<String, Vec<String>>) -> anyhow::Result<Self> {
+        let mut configured: Vec<(KeyChord, A)> = Vec::new();
+        let mut rebound = Vec::new();
+        for (name, chords) in overrides {
+            let action =
+                A::from_str(name, true).map_err(|_| anyhow::anyhow!("unknown action: {}", name))?;
+            rebound.push(action);
+            for chord in chords {
+                let chord: KeyChord = chord.parse()?;
+                if chord.is_printable() {
+                    return Err(anyhow::anyhow!(
+                        "{} types a character and cannot be bound to {}",
+                        chord,
+                        name,
+                    ));
+                }
+                if let Some((_, other)) = configured
+                    .iter()
+                    .find(|(bound, other)| *bound == chord && *other != action)
+                {
+                    return Err(anyhow::anyhow!(
+                        "{} is bound to both {} and {}",
+                        chord,
+                        self::name(other),
+                        name,
+                    ));
+                }
+                configured.push((chord, action));
+            }
+        }
+
+        self.bindings.retain(|(chord, action)| {
+            !rebound.contains(action) && !configured.iter().any(|(bound, _)| bound == chord)
+        });
+        self.bindings.extend(configured);
+        Ok(self)
+    }
+
+    /// Returns the action bound to the key pressed, if any.
+    pub fn get(&self, event: &Event) -> Option<A> {
+        let chord = KeyChord::from_event(event)?;
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, action)| *action)
+    }
+
+    /// Returns the first of the given actions bound to the key pressed, if any,
+    /// for the prompts whose actions share the keys of others (e.g. `enter`).
+    pub fn get_among(&self, event: &Event, actions: &[A]) -> Option<A> {
+        let chord = KeyChord::from_event(event)?;
+        self.bindings
+            .iter()
+            .find(|(bound, action)| *bound == chord && actions.contains(action))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(KeyChord, A)> {
+        self.bindings.iter()
+    }
+}
+
+/// Key bindings of a mode, of its query and of the prompts opened from it (e.g. to export),
+/// both configured in the table of the mode.
+#[derive(Clone)]
+pub struct ModeKeybindings<A, P> {
+    pub query: Keybindings<A>,
+    pub prompts: Keybindings<P>,
+}
+
+impl<A: ValueEnum + Copy + PartialEq, P: ValueEnum + Copy + PartialEq> ModeKeybindings<A, P> {
+    /// Rebinds the actions of the prompts and those of the query given by name.
+    pub fn configure(self, overrides: &HashMap<String, Vec<String>>) -> anyhow::Result<Self> {
+        let (prompts, query): (HashMap<_, _>, HashMap<_, _>) = overrides
+            .iter()
+            .map(|(name, chords)| (name.clone(), chords.clone()))
+            .partition(|(name, _)| P::from_str(name, true).is_ok());
+        Ok(Self {
+            query: self.query.configure(&query)?,
+            prompts: self.prompts.configure(&prompts)?,
+        })
+    }
+}
+
+/// Returns the name of the action as written in the configuration file.
+pub fn name<A: ValueEnum>(action: &A) -> String {
+    action
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+    enum Action {
+        Search,
+        Exit,
+        Backward,
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn presets() -> Keybindings<Action> {
+        Keybindings::new([
+            (KeyChord::ctrl('r'), Action::Search),
+            (KeyChord::ctrl('c'), Action::Exit),
+            (KeyChord::ctrl('b'), Action::Backward),
+            (KeyChord::key(KeyCode::Left), Action::Backward),
+        ])
+    }
+
+    fn overrides(bindings: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        bindings
+            .iter()
+            .map(|(name, chords)| {
+                (
+                    name.to_string(),
+                    chords.iter().map(|chord| chord.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_chords() {
+        let parse = |s: &str| s.parse::<KeyChord>().unwrap();
+        assert_eq!(parse("ctrl+a"), KeyChord::ctrl('a'));
+        assert_eq!(parse("Control+A"), KeyChord::ctrl('a'));
+        assert_eq!(parse("alt+B"), KeyChord::alt('b'));
+        assert_eq!(parse("shift+tab"), KeyChord::key(KeyCode::BackTab));
+        assert_eq!(parse("ctrl++"), KeyChord::ctrl('+'));
+        assert_eq!(parse("+"), KeyChord::key(KeyCode::Char('+')));
+        assert_eq!(parse("f12"), KeyChord::key(KeyCode::F(12)));
+        assert_eq!(parse("space"), KeyChord::key(KeyCode::Char(' ')));
+        assert_eq!(parse("Q"), KeyChord::key(KeyCode::Char('Q')));
+        assert_eq!(
+            parse("ctrl+shift+up"),
+            KeyChord {
+                code: KeyCode::Up,
+                modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_chords() {
+        for s in ["", "hyper+a", "ctrl+", "fx", "ctrl+ab", "pgup"] {
+            assert!(s.parse::<KeyChord>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn writes_chords_as_they_are_parsed() {
+        for s in [
+            "ctrl+a",
+            "alt+b",
+            "shift+tab",
+            "ctrl++",
+            "f12",
+            "space",
+            "enter",
+            "pageup",
+        ] {
+            assert_eq!(s.parse::<KeyChord>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn matches_key_presses_ignoring_shift_on_characters() {
+        let chord = KeyChord::from_event(&press(KeyCode::Char('?'), KeyModifiers::SHIFT));
+        assert_eq!(chord, Some(KeyChord::key(KeyCode::Char('?'))));
+        let chord = KeyChord::from_event(&press(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert_eq!(chord, Some(KeyChord::key(KeyCode::BackTab)));
+    }
+
+    #[test]
+    fn rebinds_actions_and_takes_their_chords_from_the_presets() {
+        let keybindings = presets()
+            .configure(&overrides(&[("search", &["ctrl+b"])]))
+            .unwrap();
+        let get = |code, modifiers| keybindings.get(&press(code, modifiers));
+        assert_eq!(
+            get(KeyCode::Char('b'), KeyModifiers::CONTROL),
+            Some(Action::Search)
+        );
+        assert_eq!(get(KeyCode::Char('r'), KeyModifiers::CONTROL), None);
+        assert_eq!(
+            get(KeyCode::Left, KeyModifiers::NONE),
+            Some(Action::Backward)
+        );
+        assert_eq!(
+            get(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Action::Exit)
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_printable_and_unknown_bindings() {
+        let conflict = presets().configure(&overrides(&[
+            ("search", &["ctrl+x"]),
+            ("exit", &["ctrl+x"]),
+        ]));
+        assert!(conflict.is_err());
+        let printable = presets().configure(&overrides(&[("exit", &["q"])]));
+        assert!(printable.is_err());
+        let unknown = presets().configure(&overrides(&[("jump", &["ctrl+j"])]));
+        assert!(unknown.is_err());
+        let shared = presets().configure(&overrides(&[("exit", &["ctrl+x", "ctrl+x"])]));
+        assert!(shared.is_ok());
+    }
+
+    #[test]
+    fn finds_the_first_of_the_given_actions() {
+        let keybindings = Keybindings::new([
+            (KeyChord::key(KeyCode::Enter), Action::Search),
+            (KeyChord::key(KeyCode::Enter), Action::Exit),
+        ]);
+        let enter = press(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(keybindings.get(&enter), Some(Action::Search));
+        assert_eq!(
+            keybindings.get_among(&enter, &[Action::Exit, Action::Backward]),
+            Some(Action::Exit)
+        );
+        assert_eq!(keybindings.get_among(&enter, &[Action::Backward]), None);
+    }
+}
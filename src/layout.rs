@@ -0,0 +1,113 @@
+use promkit::{
+    crossterm::style::ContentStyle,
+    grapheme::{StyledGrapheme, StyledGraphemes},
+};
+
+/// Shown in place of the text cut off from truncated lines.
+const ELLIPSIS: char = '…';
+
+/// How lines longer than the width are laid out.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Whether long lines are cut at the width rather than wrapped.
+    pub truncate: bool,
+    /// Columns that truncated lines are scrolled to the right.
+    pub hscroll: usize,
+}
+
+impl Layout {
+    pub fn toggle_wrap(&mut self) {
+        self.truncate = !self.truncate;
+        self.hscroll = 0;
+    }
+
+    /// Scrolls truncated lines by half the width, to the left if `forward` is false.
+    pub fn scroll(&mut self, forward: bool, width: u16) {
+        if !self.truncate {
+            return;
+        }
+        let step = (width as usize / 2).max(1);
+        self.hscroll = if forward {
+            self.hscroll + step
+        } else {
+            self.hscroll.saturating_sub(step)
+        };
+    }
+
+    /// Returns the rows of a line whose first match starts at the `first_match`-th grapheme.
+    pub fn rows(
+        &self,
+        styled: StyledGraphemes,
+        first_match: Option<usize>,
+        size: (u16, u16),
+    ) -> Vec<StyledGraphemes> {
+        if self.truncate {
+            vec![self.truncated(styled, first_match, size.0 as usize)]
+        } else {
+            styled.matrixify(size.0 as usize, size.1 as usize, 0).0
+        }
+    }
+
+    fn truncated(
+        &self,
+        styled: StyledGraphemes,
+        first_match: Option<usize>,
+        width: usize,
+    ) -> StyledGraphemes {
+        let total = styled.widths();
+        let mut offset = self.hscroll.min(total.saturating_sub(width));
+        // Bring the first match into view if it is beyond the width or under the ellipsis,
+        // with a quarter of the width of text before it, unless scrolled by hand.
+        if let Some(index) = first_match.filter(|_| self.hscroll == 0) {
+            let column: usize = styled.iter().take(index).map(StyledGrapheme::width).sum();
+            if column + 1 >= width {
+                offset = column
+                    .saturating_sub(width / 4)
+                    .min(total.saturating_sub(width));
+            }
+        }
+
+        let mut row = StyledGraphemes::default();
+        let mut column = 0;
+        for grapheme in styled.iter() {
+            if column >= offset && column + grapheme.width() <= offset + width {
+                row.push_back(grapheme.clone());
+            }
+            column += grapheme.width();
+        }
+
+        if offset > 0 {
+            replace_with_ellipsis(&mut row, true);
+        }
+        if total > offset + width {
+            replace_with_ellipsis(&mut row, false);
+        }
+        row
+    }
+}
+
+/// Replaces graphemes at the front (or back) of the row with an ellipsis, keeping its width.
+fn replace_with_ellipsis(row: &mut StyledGraphemes, front: bool) {
+    let mut freed = 0;
+    while freed < 1 {
+        let removed = if front {
+            row.pop_front()
+        } else {
+            row.pop_back()
+        };
+        match removed {
+            Some(grapheme) => freed += grapheme.width(),
+            None => break,
+        }
+    }
+    let padding = (1..freed).map(|_| StyledGrapheme::new(' ', ContentStyle::default()));
+    let ellipsis = StyledGrapheme::new(ELLIPSIS, ContentStyle::default());
+    if front {
+        for grapheme in padding.chain([ellipsis]) {
+            row.push_front(grapheme);
+        }
+    } else {
+        row.extend(padding);
+        row.push_back(ellipsis);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{ops::Range, sync::OnceLock};
+
+use promkit::{crossterm::style::ContentStyle, serde_json::Value};
+use regex::Regex;
+
+use crate::{json, logfmt, pod};
+
+/// Keys of the field holding the level in JSON and logfmt records.
+const KEYS: [&str; 4] = ["level", "lvl", "severity", "loglevel"];
+
+/// Severity of a line, from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Returns the level named e.g. `WARN`, `warning` or `err`, in any case.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "trace" | "trc" => Some(Level::Trace),
+            "debug" | "dbg" => Some(Level::Debug),
+            "info" | "inf" | "information" | "notice" => Some(Level::Info),
+            "warn" | "warning" | "wrn" => Some(Level::Warn),
+            "error" | "err" | "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" => {
+                Some(Level::Error)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the level of a numeric severity as written by bunyan or pino, e.g. 50 for error.
+    fn from_number(number: f64) -> Option<Self> {
+        match number as u64 {
+            10..=19 => Some(Level::Trace),
+            20..=29 => Some(Level::Debug),
+            30..=39 => Some(Level::Info),
+            40..=49 => Some(Level::Warn),
+            50.. => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// Level words in capitals (e.g. `ERROR`) anywhere, or in any case between brackets (`[warn]`),
+/// and klog headers (e.g. `E0412 10:32:00.123456`) at the start.
+fn words() -> &'static Regex {
+    static WORDS: OnceLock<Regex> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        let names = "TRACE|TRC|DEBUG|DBG|INFO|INF|NOTICE|WARN|WARNING|WRN|ERROR|ERR|FATAL|CRITICAL|CRIT|PANIC";
+        Regex::new(&format!(
+            r"^(?P<klog>[IWEF])\d{{4}} |\b(?P<word>{names})\b|\[(?P<bracketed>(?i:{names}))\]"
+        ))
+        .unwrap()
+    })
+}
+
+/// Returns the level of the line and where it is written, if any is found.
+pub fn detect(line: &str) -> Option<(Level, Range<usize>)> {
+    // The message after a pod prefix is read as a record, whose spans are shifted back.
+    let start = pod::message_start(line);
+    let message = &line[start..];
+    if let Some(record) = json::record(message) {
+        return KEYS.iter().find_map(|key| {
+            let level = match record.get(*key)? {
+                Value::String(name) => Level::from_name(name)?,
+                Value::Number(number) => Level::from_number(number.as_f64()?)?,
+                _ => return None,
+            };
+            let span = json::Condition::parse(&format!("{}=_", key))?.locate(message)?;
+            Some((level, start + span.start()..start + span.end()))
+        });
+    }
+    if let Some(fields) = logfmt::parse_from(line, start) {
+        if let Some(found) = KEYS.iter().find_map(|key| {
+            let field = fields
+                .iter()
+                .find(|field| &line[field.key.clone()] == *key)?;
+            Some((
+                Level::from_name(&field.value(line))?,
+                field.key.start..field.value.end,
+            ))
+        }) {
+            return Some(found);
+        }
+    }
+
+    let captures = words().captures(line)?;
+    if let Some(klog) = captures.name("klog") {
+        let level = match klog.as_str() {
+            "I" => Level::Info,
+            "W" => Level::Warn,
+            _ => Level::Error,
+        };
+        return Some((level, 0..captures.get(0)?.end() - 1));
+    }
+    let name = captures
+        .name("word")
+        .or_else(|| captures.name("bracketed"))?;
+    Some((Level::from_name(name.as_str())?, name.range()))
+}
+
+/// How the levels of lines are shown and filtered.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Levels {
+    /// Whether the level of each line is colored.
+    pub colored: bool,
+    /// Level below which lines, and those without any level, are filtered out.
+    pub minimum: Option<Level>,
+    /// Styles of the levels, from trace to error.
+    pub styles: [ContentStyle; 5],
+}
+
+impl Levels {
+    /// Raises the minimum level by one, back to none after error.
+    pub fn cycle_minimum(&mut self) {
+        self.minimum = match self.minimum {
+            None => Some(Level::Debug),
+            Some(Level::Trace | Level::Debug) => Some(Level::Info),
+            Some(Level::Info) => Some(Level::Warn),
+            Some(Level::Warn) => Some(Level::Error),
+            Some(Level::Error) => None,
+        };
+    }
+
+    /// Returns whether a line of the level, as detected, is at or above the minimum level.
+    pub fn admits(&self, level: Option<Level>) -> bool {
+        self.minimum
+            .is_none_or(|minimum| level.is_some_and(|level| level >= minimum))
+    }
+
+    pub fn style(&self, level: Level) -> ContentStyle {
+        self.styles[level as usize]
+    }
+
+    /// Describes the minimum level, e.g. for the settings.
+    pub fn describe_minimum(&self) -> String {
+        match self.minimum {
+            Some(minimum) => format!("{} and above", minimum.name()),
+            None => String::from("all lines"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the level detected in the line, with the text it was detected by.
+    fn detected(line: &str) -> Option<(Level, &str)> {
+        detect(line).map(|(level, span)| (level, &line[span]))
+    }
+
+    #[test]
+    fn detects_levels_of_records() {
+        assert_eq!(
+            detected(r#"{"msg": "x", "level": "WARNING"}"#),
+            Some((Level::Warn, r#""level": "WARNING""#))
+        );
+        assert_eq!(
+            detected(r#"{"level": 50, "msg": "failed"}"#),
+            Some((Level::Error, r#""level": 50"#))
+        );
+        assert_eq!(
+            detected("ts=1 severity=debug msg=x"),
+            Some((Level::Debug, "severity=debug"))
+        );
+        // A record without a level is not searched for words.
+        assert_eq!(detected(r#"{"msg": "ERROR in the message"}"#), None);
+    }
+
+    #[test]
+    fn detects_levels_of_text() {
+        assert_eq!(
+            detected("E0412 10:32:00.123456 1 main.go:10] failed"),
+            Some((Level::Error, "E0412"))
+        );
+        assert_eq!(
+            detected("10:32:00 [warn] slow"),
+            Some((Level::Warn, "warn"))
+        );
+        assert_eq!(
+            detected("10:32:00 INFO started"),
+            Some((Level::Info, "INFO"))
+        );
+        // Lowercase words outside brackets are prose.
+        assert_eq!(detected("no error here"), None);
+        assert_eq!(detected("INFORMATION"), None);
+    }
+
+    #[test]
+    fn detects_levels_after_pod_prefixes() {
+        let line = r#"[pod/api-7d9c8/app] {"level": "error", "msg": "x"}"#;
+        assert_eq!(detected(line), Some((Level::Error, r#""level": "error""#)));
+    }
+
+    #[test]
+    fn admits_lines_at_or_above_the_minimum() {
+        let mut levels = Levels::default();
+        assert!(levels.admits(None));
+        levels.cycle_minimum();
+        levels.cycle_minimum();
+        assert_eq!(levels.minimum, Some(Level::Info));
+        assert!(levels.admits(Some(Level::Error)));
+        assert!(levels.admits(Some(Level::Info)));
+        assert!(!levels.admits(Some(Level::Debug)));
+        assert!(!levels.admits(None));
+        levels.cycle_minimum();
+        levels.cycle_minimum();
+        levels.cycle_minimum();
+        assert_eq!(levels.minimum, None);
+    }
+}
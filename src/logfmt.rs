@@ -0,0 +1,265 @@
+use std::ops::Range;
+
+use grep::matcher::Match;
+use promkit::{
+    crossterm::style::ContentStyle,
+    grapheme::{StyledGrapheme, StyledGraphemes},
+    serde_json::{self, Map, Value},
+};
+
+/// A `key=value` pair of a logfmt line, by its byte ranges in the line.
+pub struct Field {
+    pub key: Range<usize>,
+    /// The value as written, with any quotes, which is empty for a bare key.
+    pub value: Range<usize>,
+}
+
+impl Field {
+    /// Returns the value without its quotes and escapes.
+    pub fn value(&self, line: &str) -> String {
+        let raw = &line[self.value.clone()];
+        if raw.starts_with('"') {
+            serde_json::from_str(raw).unwrap_or_else(|_| raw.trim_matches('"').to_string())
+        } else {
+            raw.to_string()
+        }
+    }
+
+    /// Returns where the field ends, including its value if it has one.
+    fn end(&self) -> usize {
+        self.value.end.max(self.key.end)
+    }
+}
+
+/// Returns the fields of the line if it is in logfmt, e.g. `level=info msg="started" port=8080`.
+/// Every word must be a key, with or without a value, and at least half of them with one,
+/// so that prose with an `=` here and there is not taken for logfmt.
+pub fn parse(line: &str) -> Option<Vec<Field>> {
+    parse_from(line, 0)
+}
+
+/// Parses the rest of the line from `start` on, e.g. the message after a pod prefix,
+/// with the fields still ranged in the whole line.
+pub fn parse_from(line: &str, start: usize) -> Option<Vec<Field>> {
+    let bytes = line.as_bytes();
+    let mut fields = Vec::new();
+    let mut valued = 0;
+    let mut i = start;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == bytes.len() {
+            break;
+        }
+
+        let start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'=' | b'"')
+        {
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+        let key = start..i;
+
+        let value = if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            valued += 1;
+            let start = i;
+            if bytes.get(i) == Some(&b'"') {
+                i += 1;
+                let mut escaped = false;
+                loop {
+                    match bytes.get(i)? {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => break,
+                        _ => (),
+                    }
+                    i += 1;
+                }
+                i += 1;
+            } else {
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+            }
+            start..i
+        } else {
+            i..i
+        };
+        // Anything but a space after a field, e.g. a quote after a key, is not logfmt.
+        if i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            return None;
+        }
+        fields.push(Field { key, value });
+    }
+
+    (valued > 0 && valued * 2 >= fields.len()).then_some(fields)
+}
+
+/// Returns the fields as a JSON object of strings, for conditions on them to be tested against.
+pub fn record(line: &str, fields: &[Field]) -> Value {
+    Value::Object(
+        fields
+            .iter()
+            .map(|field| {
+                (
+                    line[field.key.clone()].to_string(),
+                    Value::String(field.value(line)),
+                )
+            })
+            .collect::<Map<_, _>>(),
+    )
+}
+
+/// Returns where the field of the key is in the line, from its key to the end of its value.
+pub fn locate(line: &str, fields: &[Field], key: &str) -> Option<Match> {
+    fields
+        .iter()
+        .find(|field| &line[field.key.clone()] == key)
+        .map(|field| Match::new(field.key.start, field.end()))
+}
+
+/// How the fields of logfmt lines are shown.
+#[derive(Clone, Default)]
+pub struct Fields {
+    pub key_style: ContentStyle,
+    pub value_style: ContentStyle,
+    /// Keys of the fields left out.
+    pub hidden: Vec<String>,
+    /// Keys of the only fields shown, unless empty.
+    pub shown: Vec<String>,
+}
+
+impl Fields {
+    fn visible(&self, key: &str) -> bool {
+        !self.hidden.iter().any(|hidden| hidden == key)
+            && (self.shown.is_empty() || self.shown.iter().any(|shown| shown == key))
+    }
+
+    /// Returns the line with its keys and values styled, to be highlighted over.
+    pub fn colored(&self, line: &str, fields: &[Field]) -> StyledGraphemes {
+        let mut styled = StyledGraphemes::default();
+        let mut offset = 0;
+        for field in fields {
+            styled.extend(StyledGraphemes::from(&line[offset..field.key.start]).0);
+            styled.extend(StyledGraphemes::from_str(&line[field.key.clone()], self.key_style).0);
+            styled.extend(StyledGraphemes::from(&line[field.key.end..field.value.start]).0);
+            styled
+                .extend(StyledGraphemes::from_str(&line[field.value.clone()], self.value_style).0);
+            offset = field.end();
+        }
+        styled.extend(StyledGraphemes::from(&line[offset..]).0);
+        styled
+    }
+
+    /// Leaves the hidden fields (and the spaces around them) out of the styled line,
+    /// and moves `index`, e.g. where the first match starts, along with the graphemes.
+    /// The line is left as it is if none of its fields would remain.
+    pub fn hide(
+        &self,
+        line: &str,
+        fields: &[Field],
+        styled: StyledGraphemes,
+        index: Option<usize>,
+    ) -> (StyledGraphemes, Option<usize>) {
+        if !fields
+            .iter()
+            .any(|field| self.visible(&line[field.key.clone()]))
+        {
+            return (styled, index);
+        }
+
+        let hidden: Vec<Range<usize>> = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !self.visible(&line[field.key.clone()]))
+            .map(|(i, field)| match fields.get(i + 1) {
+                Some(next) => field.key.start..next.key.start,
+                // The last field takes the spaces before it rather than after.
+                None => {
+                    i.checked_sub(1)
+                        .map_or(field.key.start, |i| fields[i].end())
+                        ..line.len()
+                }
+            })
+            .collect();
+
+        let mut kept = StyledGraphemes::default();
+        let mut moved = None;
+        for (i, ((offset, _), grapheme)) in line.char_indices().zip(styled.iter()).enumerate() {
+            if index == Some(i) {
+                moved = Some(kept.len());
+            }
+            if !hidden.iter().any(|range| range.contains(&offset)) {
+                kept.push_back(StyledGrapheme::clone(grapheme));
+            }
+        }
+        (kept, index.and(moved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(line: &str) -> Option<Vec<(String, String)>> {
+        parse(line).map(|fields| {
+            fields
+                .iter()
+                .map(|field| (line[field.key.clone()].to_string(), field.value(line)))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn parses_keys_and_values() {
+        assert_eq!(
+            pairs(r#"level=info msg="started \"api\"" port=8080 debug"#).unwrap(),
+            [
+                ("level", "info"),
+                ("msg", "started \"api\""),
+                ("port", "8080"),
+                ("debug", ""),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        assert_eq!(
+            pairs("empty= next=1").unwrap()[0],
+            ("empty".into(), "".into())
+        );
+    }
+
+    #[test]
+    fn leaves_out_prose() {
+        assert!(pairs("set the limit to n=5 before starting").is_none());
+        assert!(pairs("no fields at all").is_none());
+        assert!(pairs("").is_none());
+        // An unterminated quote or a quote after a key is not logfmt either.
+        assert!(pairs(r#"msg="open level=info"#).is_none());
+        assert!(pairs(r#"msg"x"=1"#).is_none());
+    }
+
+    #[test]
+    fn parses_from_an_offset_in_the_whole_line() {
+        let line = "[pod/api-7d9c8/app] level=warn msg=slow";
+        let start = line.find(']').unwrap() + 2;
+        let fields = parse_from(line, start).unwrap();
+        assert_eq!(&line[fields[0].key.clone()], "level");
+        assert_eq!(fields[1].value(line), "slow");
+    }
+
+    #[test]
+    fn locates_fields_by_key() {
+        let line = r#"level=info msg="a b" flag"#;
+        let fields = parse(line).unwrap();
+        let found = locate(line, &fields, "msg").unwrap();
+        assert_eq!(&line[found.start()..found.end()], r#"msg="a b""#);
+        let found = locate(line, &fields, "flag").unwrap();
+        assert_eq!(&line[found.start()..found.end()], "flag");
+        assert!(locate(line, &fields, "missing").is_none());
+        assert_eq!(record(line, &fields)["msg"], "a b");
+    }
+}
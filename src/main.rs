@@ -1,6 +1,6 @@
-use std::{collections::VecDeque, io};
+use std::{collections::VecDeque, io, path::PathBuf};
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use tokio::{
     sync::mpsc,
     time::{timeout, Duration},
@@ -9,20 +9,37 @@ use tokio_util::sync::CancellationToken;
 
 use promkit::{
     crossterm::{
-        self, cursor, execute,
-        style::Color,
+        cursor, execute,
+        style::ContentStyle,
         terminal::{disable_raw_mode, enable_raw_mode},
     },
-    listbox,
-    style::StyleBuilder,
     text_editor,
 };
 
 mod archived;
+mod clock;
 mod cmd;
+mod config;
+mod dedupe;
+mod editing;
+mod extract;
+mod gutter;
+mod headless;
+mod help;
+mod history;
+mod json;
+mod keybinding;
+mod layout;
+mod level;
+mod logfmt;
+mod output;
+mod pod;
 mod sig;
+mod stats;
 mod stdin;
 mod terminal;
+mod timestamp;
+mod vi;
 
 #[derive(Eq, PartialEq)]
 pub enum Signal {
@@ -52,6 +69,9 @@ $ cat README.md |& sig -a
 Or
 $ sig -a --cmd \"cat README.md\"
 
+Without the interactive interface:
+$ cat README.md | sig --no-tui --query \"sig\"
+
 Options:
 {options}
 "
@@ -94,6 +114,137 @@ pub struct Args {
     )]
     pub archived: bool,
 
+    #[arg(
+        long = "alternate-screen",
+        default_value = "false",
+        help = "Draw streaming mode on the alternate screen.",
+        long_help = "Draw streaming mode on the alternate screen,
+        where the lines kept in the queue can be scrolled back
+        with PageUp/PageDown or the mouse wheel
+        rather than mixing with the scrollback of the shell,
+        which is restored as it was on exit."
+    )]
+    pub alternate_screen: bool,
+
+    #[arg(
+        long = "height",
+        conflicts_with = "alternate_screen",
+        help = "Rows at the bottom of the terminal to draw in, e.g. 20 or 40%.",
+        long_help = "Number of rows, or percentage of the terminal height,
+        to draw in at the bottom of the terminal
+        instead of clearing it, leaving the output of the shell visible above."
+    )]
+    pub height: Option<terminal::Height>,
+
+    #[arg(
+        long = "truncate",
+        default_value = "false",
+        help = "Truncate long lines instead of wrapping them in streaming mode.",
+        long_help = "Cut lines longer than the terminal width with an ellipsis
+        instead of wrapping them in streaming mode,
+        scrolled so that the first match is in view unless scrolled by hand.
+        This can also be toggled by key mappings."
+    )]
+    pub truncate: bool,
+
+    #[arg(
+        short = 'n',
+        long = "line-number",
+        default_value = "false",
+        help = "Show the position of each line in the stream.",
+        long_help = "Show the position of each line in the whole stream
+        (rather than in the queue) in a gutter before it."
+    )]
+    pub line_number: bool,
+
+    #[arg(
+        long = "timestamp",
+        default_value = "false",
+        help = "Show the time each line arrived.",
+        long_help = "Show the local time each line was received in a gutter before it,
+        regardless of any timestamp in the line itself."
+    )]
+    pub timestamp: bool,
+
+    #[arg(
+        long = "relative-time",
+        default_value = "false",
+        help = "Show how long ago each line was logged.",
+        long_help = "Show how long ago (e.g. 12s ago) each line was logged
+        in a gutter before it, by the timestamp in the line
+        or else the time it was received."
+    )]
+    pub relative_time: bool,
+
+    #[arg(
+        long = "time-format",
+        help = "strftime format of the timestamps in lines, e.g. \"%Y/%m/%d %H:%M:%S\".",
+        long_help = "strftime format of the timestamps in lines (e.g. \"%Y/%m/%d %H:%M:%S\"),
+        tried before the known ones: RFC3339 and the like, syslog, nginx and klog.
+        Timestamps are used for --relative-time and for the time filter of archived mode."
+    )]
+    pub time_format: Option<String>,
+
+    #[arg(
+        long = "levels",
+        default_value = "false",
+        help = "Color the level of each line.",
+        long_help = "Color the level of each line (error, warn, info, debug or trace),
+        found in its level or severity field if it is a JSON or logfmt record,
+        in a klog header (e.g. E0412), or as a word such as ERROR or [warn].
+        The lines can be filtered by a minimum level by key mappings regardless."
+    )]
+    pub levels: bool,
+
+    #[arg(
+        long = "dedupe",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "exact",
+        help = "Collapse consecutive duplicate lines into one with a count.",
+        long_help = "Collapse consecutive duplicate lines into one,
+        counted (e.g. ×12) in a gutter before it and shown as the latest of them,
+        in both streaming and archived mode.
+        With masked, lines differing only in numbers, timestamps and UUIDs
+        are duplicates too."
+    )]
+    pub dedupe: Option<dedupe::Dedupe>,
+
+    #[arg(
+        short = 'o',
+        long = "only-captures",
+        default_value = "false",
+        help = "Show only the text captured by the groups of the query, e.g. status=(\\d+).",
+        long_help = "Show only the text captured by the capture groups of the query
+        (e.g. 200 of status=200 with status=(\\d+)) instead of whole lines,
+        leaving out the lines capturing nothing,
+        in both streaming and archived mode and with --no-tui.
+        The lines printed on exit or exported in archived mode are the captures too.
+        This can also be toggled by key mappings."
+    )]
+    pub only_captures: bool,
+
+    #[arg(
+        long = "hide-keys",
+        value_delimiter = ',',
+        help = "Keys of the fields to leave out of logfmt lines, e.g. ts,caller.",
+        long_help = "Comma-separated keys of the fields to leave out
+        when showing logfmt (key=value) lines,
+        which can still be searched and are printed as they are."
+    )]
+    pub hide_keys: Vec<String>,
+
+    #[arg(
+        long = "show-keys",
+        value_delimiter = ',',
+        conflicts_with = "hide_keys",
+        help = "Keys of the only fields to show of logfmt lines, e.g. level,msg.",
+        long_help = "Comma-separated keys of the only fields to show
+        of logfmt (key=value) lines, leaving the others out.
+        Lines without any of these fields are shown as they are."
+    )]
+    pub show_keys: Vec<String>,
+
     #[arg(
         short = 'i',
         long = "ignore-case",
@@ -102,6 +253,28 @@ pub struct Args {
     )]
     pub case_insensitive: bool,
 
+    #[arg(
+        short = 'C',
+        long = "context",
+        default_value = "5",
+        help = "Number of lines to show around a line expanded in archived mode.",
+        long_help = "Number of lines to show before and after the selected line
+        when it is expanded into its surrounding context in archived mode."
+    )]
+    pub context_lines: usize,
+
+    #[arg(
+        long = "columns",
+        value_delimiter = ',',
+        help = "Fields to show as columns in archived mode, e.g. ts,level,msg.",
+        long_help = "Comma-separated fields of JSON, logfmt, CSV or TSV records
+        to show as aligned columns under a header in archived mode,
+        where nested JSON fields are named by their paths (e.g. http.status)
+        and CSV or TSV columns by their first line.
+        The columns can also be picked by key mappings."
+    )]
+    pub columns: Vec<String>,
+
     #[arg(
         long = "cmd",
         help = "Command to execute on initial and retries.",
@@ -109,10 +282,59 @@ pub struct Args {
         whenever a retry is triggered according to key mappings."
     )]
     pub cmd: Option<String>,
+
+    #[arg(
+        long = "query",
+        help = "Initial query.",
+        long_help = "Query to filter lines with from the start.
+        This is also the query used with --no-tui."
+    )]
+    pub query: Option<String>,
+
+    #[arg(
+        long = "no-tui",
+        default_value = "false",
+        help = "Print lines matching the query to stdout without the interactive interface."
+    )]
+    pub no_tui: bool,
+
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t,
+        help = "Format of the lines printed with --no-tui."
+    )]
+    pub format: output::Format,
+
+    #[arg(
+        long = "keymap",
+        value_enum,
+        default_value_t,
+        help = "Preset of key bindings.",
+        long_help = "Preset of key bindings, on top of which
+        actions can be rebound in the configuration file
+        (under [keybindings.streaming] and [keybindings.archived])."
+    )]
+    pub keymap: keybinding::Preset,
+
+    #[arg(
+        long = "config",
+        help = "Path to the configuration file.",
+        long_help = "Path to the configuration file
+        setting the defaults of these options (under [options]),
+        the prompts and styles (under [theme])
+        and the key bindings (under [keybindings]).
+        Defaults to $XDG_CONFIG_HOME/sig/config.toml or ~/.config/sig/config.toml."
+    )]
+    pub config: Option<PathBuf>,
 }
 
 impl Drop for Args {
     fn drop(&mut self) {
+        // Nothing to restore since the terminal was never taken over.
+        if self.no_tui {
+            return;
+        }
         disable_raw_mode().ok();
         execute!(io::stdout(), cursor::Show).ok();
     }
@@ -120,12 +342,94 @@ impl Drop for Args {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let loaded = config::Config::load().and_then(|config| {
+        let command = config.apply(Args::command())?;
+        Ok((config, command))
+    });
+    let (config, command) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            // Lets `--help` and `--version` work in spite of a broken config,
+            // reporting it only if neither is asked for.
+            config::Config::default()
+                .apply(Args::command())?
+                .get_matches();
+            return Err(e);
+        }
+    };
+    let args = Args::from_arg_matches(&command.get_matches())?;
+    let query = args.query.clone().unwrap_or_default();
+
+    let theme = config.theme;
+    let highlight_style = ContentStyle::from(&theme.highlight);
+    let timestamps = timestamp::Parser {
+        format: args.time_format.clone(),
+    };
+    let gutter = gutter::Gutter {
+        number: args.line_number,
+        time: args.timestamp,
+        relative: args.relative_time.then(|| timestamps.clone()),
+        repeats: args.dedupe.is_some(),
+        style: ContentStyle::from(&theme.gutter),
+    };
+    let fields = logfmt::Fields {
+        key_style: ContentStyle::from(&theme.field_key),
+        value_style: ContentStyle::from(&theme.field_value),
+        hidden: args.hide_keys.clone(),
+        shown: args.show_keys.clone(),
+    };
+    let levels = level::Levels {
+        colored: args.levels,
+        minimum: None,
+        styles: [
+            &theme.level_trace,
+            &theme.level_debug,
+            &theme.level_info,
+            &theme.level_warn,
+            &theme.level_error,
+        ]
+        .map(ContentStyle::from),
+    };
+
+    if args.no_tui {
+        return headless::run(
+            &query,
+            args.format,
+            highlight_style,
+            Duration::from_millis(args.retrieval_timeout_millis),
+            args.case_insensitive,
+            args.only_captures,
+            args.cmd.clone(),
+        )
+        .await;
+    }
+
+    let streaming_keybindings = sig::keybindings(args.keymap, &config.keybindings.streaming)?;
+    let archived_keybindings = archived::keybindings(args.keymap, &config.keybindings.archived)?;
+
+    let output = output::Output::redirect()?;
 
     enable_raw_mode()?;
     execute!(io::stdout(), cursor::Hide)?;
+    let mut screen = terminal::Screen::enter(args.alternate_screen, args.height)?;
 
-    let highlight_style = StyleBuilder::new().fgc(Color::Red).build();
+    let archived_options = archived::Options {
+        theme: &theme,
+        gutter: gutter.clone(),
+        highlight_style,
+        fields: fields.clone(),
+        levels,
+        case_insensitive: args.case_insensitive,
+        dedupe: args.dedupe,
+        only_captures: args.only_captures,
+        context_lines: args.context_lines,
+        columns: args.columns.clone(),
+        timestamps: timestamps.clone(),
+        height: screen.height(),
+        keybindings: archived_keybindings,
+        // In archived mode alone, command for retry is meaningless.
+        cmd: None,
+    };
 
     if args.archived {
         let (tx, mut rx) = mpsc::channel(1);
@@ -151,7 +455,8 @@ async fn main() -> anyhow::Result<()> {
             });
         }
 
-        let mut queue = VecDeque::with_capacity(args.queue_capacity);
+        let mut queue = VecDeque::<gutter::Line>::with_capacity(args.queue_capacity);
+        let mut number = 0;
         loop {
             match timeout(
                 Duration::from_millis(args.retrieval_timeout_millis),
@@ -159,117 +464,95 @@ async fn main() -> anyhow::Result<()> {
             )
             .await
             {
-                Ok(Some(line)) => {
-                    if queue.len() > args.queue_capacity {
-                        queue.pop_front().unwrap();
+                Ok(Some(text)) => {
+                    number += 1;
+                    let line = gutter::Line::new(number, text);
+                    match queue.back_mut() {
+                        Some(last)
+                            if args.dedupe.is_some_and(|d| d.same(&last.text, &line.text)) =>
+                        {
+                            last.repeat(line)
+                        }
+                        _ => {
+                            if queue.len() > args.queue_capacity {
+                                queue.pop_front().unwrap();
+                            }
+                            queue.push_back(line);
+                        }
                     }
-                    queue.push_back(line.clone());
                 }
                 Ok(None) => break,
                 Err(_) => break,
             }
         }
 
-        crossterm::execute!(
-            io::stdout(),
-            crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-            crossterm::terminal::Clear(crossterm::terminal::ClearType::Purge),
-            cursor::MoveTo(0, 0),
-        )?;
+        screen.clear()?;
 
-        archived::run(
+        if let Some(lines) = archived::run(
             text_editor::State {
-                texteditor: Default::default(),
-                history: Default::default(),
-                prefix: String::from("❯❯❯ "),
-                mask: Default::default(),
-                prefix_style: StyleBuilder::new().fgc(Color::DarkBlue).build(),
-                active_char_style: StyleBuilder::new().bgc(Color::DarkCyan).build(),
-                inactive_char_style: StyleBuilder::new().build(),
-                edit_mode: Default::default(),
-                word_break_chars: Default::default(),
-                lines: Default::default(),
-            },
-            listbox::State {
-                listbox: listbox::Listbox::from_displayable(queue),
-                cursor: String::from("❯ "),
-                active_item_style: None,
-                inactive_item_style: None,
-                lines: Default::default(),
+                history: Some(history::load()),
+                ..theme.archived_text_editor(&query)
             },
-            highlight_style,
-            args.case_insensitive,
-            // In archived mode, command for retry is meaningless.
-            None,
-        )?;
+            queue.into(),
+            archived_options,
+        )? {
+            screen.leave();
+            output.print(&lines)?;
+        }
     } else {
         while let Ok((signal, queue)) = sig::run(
             text_editor::State {
-                texteditor: Default::default(),
-                history: Default::default(),
-                prefix: String::from("❯❯ "),
-                mask: Default::default(),
-                prefix_style: StyleBuilder::new().fgc(Color::DarkGreen).build(),
-                active_char_style: StyleBuilder::new().bgc(Color::DarkCyan).build(),
-                inactive_char_style: StyleBuilder::new().build(),
-                edit_mode: Default::default(),
-                word_break_chars: Default::default(),
-                lines: Default::default(),
+                history: Some(history::load()),
+                ..theme.streaming_text_editor(&query)
+            },
+            sig::Options {
+                highlight_style,
+                fields: fields.clone(),
+                levels,
+                retrieval_timeout: Duration::from_millis(args.retrieval_timeout_millis),
+                render_interval: Duration::from_millis(args.render_interval_millis),
+                queue_capacity: args.queue_capacity,
+                dedupe: args.dedupe,
+                only_captures: args.only_captures,
+                case_insensitive: args.case_insensitive,
+                alternate_screen: args.alternate_screen,
+                height: screen.height(),
+                truncate: args.truncate,
+                gutter: gutter.clone(),
+                timestamps: timestamps.clone(),
+                keybindings: streaming_keybindings.clone(),
+                cmd: args.cmd.clone(),
             },
-            highlight_style,
-            Duration::from_millis(args.retrieval_timeout_millis),
-            Duration::from_millis(args.render_interval_millis),
-            args.queue_capacity,
-            args.case_insensitive,
-            args.cmd.clone(),
         )
         .await
         {
-            crossterm::execute!(
-                io::stdout(),
-                crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-                crossterm::terminal::Clear(crossterm::terminal::ClearType::Purge),
-                cursor::MoveTo(0, 0),
-            )?;
+            screen.clear()?;
 
             match signal {
                 Signal::GotoArchived => {
-                    archived::run(
+                    if let Some(lines) = archived::run(
                         text_editor::State {
-                            texteditor: Default::default(),
-                            history: Default::default(),
-                            prefix: String::from("❯❯❯ "),
-                            mask: Default::default(),
-                            prefix_style: StyleBuilder::new().fgc(Color::DarkBlue).build(),
-                            active_char_style: StyleBuilder::new().bgc(Color::DarkCyan).build(),
-                            inactive_char_style: StyleBuilder::new().build(),
-                            edit_mode: Default::default(),
-                            word_break_chars: Default::default(),
-                            lines: Default::default(),
+                            history: Some(history::load()),
+                            ..theme.archived_text_editor(&query)
                         },
-                        listbox::State {
-                            listbox: listbox::Listbox::from_displayable(queue),
-                            cursor: String::from("❯ "),
-                            active_item_style: None,
-                            inactive_item_style: None,
-                            lines: Default::default(),
+                        queue.into(),
+                        archived::Options {
+                            cmd: args.cmd.clone(),
+                            ..archived_options.clone()
                         },
-                        highlight_style,
-                        args.case_insensitive,
-                        args.cmd.clone(),
-                    )?;
+                    )? {
+                        // Restore the terminal first, or the lines would be cleared with sig.
+                        screen.leave();
+                        output.print(&lines)?;
+                        break;
+                    }
 
                     // Re-enable raw mode and hide the cursor again here
                     // because they are disabled and shown, respectively, by promkit.
                     enable_raw_mode()?;
                     execute!(io::stdout(), cursor::Hide)?;
 
-                    crossterm::execute!(
-                        io::stdout(),
-                        crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-                        crossterm::terminal::Clear(crossterm::terminal::ClearType::Purge),
-                        cursor::MoveTo(0, 0),
-                    )?;
+                    screen.clear()?;
                 }
                 Signal::GotoStreaming => {
                     continue;
@@ -0,0 +1,94 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use grep::matcher::Match;
+use promkit::{crossterm::style::ContentStyle, serde_json};
+
+use crate::sig;
+
+/// How lines are written out of sig.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum Format {
+    /// The line as it is.
+    #[default]
+    Plain,
+    /// The line with the matches highlighted by ANSI escape sequences.
+    Highlight,
+    /// A JSON object with the line and the byte offsets of the matches.
+    Json,
+}
+
+impl Format {
+    /// Renders the line with `matches` as returned by `sig::find`.
+    pub fn render(&self, line: &str, matches: &[Match], highlight_style: ContentStyle) -> String {
+        match self {
+            Format::Plain => line.to_string(),
+            Format::Highlight => sig::highlight(line, matches, highlight_style)
+                .styled_display()
+                .to_string(),
+            Format::Json => serde_json::json!({
+                "line": line,
+                "matches": matches
+                    .iter()
+                    .map(|m| serde_json::json!({"start": m.start(), "end": m.end()}))
+                    .collect::<Vec<_>>(),
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Destination for the lines selected in archived mode.
+pub struct Output {
+    /// Original standard output, kept aside while it is redirected to the terminal.
+    original: Option<File>,
+}
+
+impl Output {
+    /// Points the standard output to the terminal if it is piped or redirected
+    /// (e.g. `$(kubectl get pods | sig -a)`), so that the interface is drawn on screen
+    /// while the selected lines can still be written to the original destination.
+    pub fn redirect() -> anyhow::Result<Self> {
+        Ok(Self {
+            original: redirect_to_tty()?,
+        })
+    }
+
+    pub fn print(self, lines: &[String]) -> anyhow::Result<()> {
+        let mut writer: Box<dyn Write> = match self.original {
+            Some(file) => Box::new(file),
+            None => Box::new(io::stdout()),
+        };
+        for line in lines {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn redirect_to_tty() -> anyhow::Result<Option<File>> {
+    use std::os::fd::{AsFd, AsRawFd};
+
+    use io::IsTerminal;
+
+    if io::stdout().is_terminal() {
+        return Ok(None);
+    }
+
+    let original = File::from(io::stdout().as_fd().try_clone_to_owned()?);
+    let tty = File::options().write(true).open("/dev/tty")?;
+    // SAFETY: Both are valid open descriptors owned by this process.
+    if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(Some(original))
+}
+
+#[cfg(not(unix))]
+fn redirect_to_tty() -> anyhow::Result<Option<File>> {
+    Ok(None)
+}
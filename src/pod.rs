@@ -0,0 +1,100 @@
+use std::{ops::Range, sync::OnceLock};
+
+use grep::matcher::Match;
+use promkit::{
+    crossterm::style::{Color, ContentStyle},
+    serde_json::{json, Value},
+};
+use regex::Regex;
+
+/// Colors of pods, told apart from the red of the matches.
+const COLORS: [Color; 10] = [
+    Color::DarkCyan,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkMagenta,
+    Color::DarkBlue,
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+];
+
+/// Prefixes of `kubectl logs --prefix`, e.g. `[pod/web-7d9c8/nginx] `,
+/// and of stern, e.g. `web-7d9c8 nginx `, whose pod names are told from ordinary words
+/// by ending like generated ones: in five characters of the alphabet Kubernetes
+/// generates them from (without vowels, 0, 1 and 3), or in the ordinal of a StatefulSet.
+fn prefixes() -> &'static [Regex; 2] {
+    static PREFIXES: OnceLock<[Regex; 2]> = OnceLock::new();
+    PREFIXES.get_or_init(|| {
+        [
+            Regex::new(r"^\[pod/(?P<pod>[^/\]\s]+)/(?P<container>[^\]\s]+)\] ?"),
+            Regex::new(
+                r"^(?P<pod>[a-z][a-z0-9.-]*-(?:[bcdfghjklmnpqrstvwxz2456789]{5}|\d+)) (?P<container>[a-z0-9](?:[a-z0-9-]*[a-z0-9])?) ",
+            ),
+        ]
+        .map(Result::unwrap)
+    })
+}
+
+/// The pod and container a line of stern or `kubectl logs --prefix` starts with.
+pub struct Prefix {
+    pub pod: Range<usize>,
+    pub container: Range<usize>,
+    /// Where the message after the prefix starts.
+    pub end: usize,
+}
+
+/// Returns the prefix of the line, if it has one.
+pub fn parse(line: &str) -> Option<Prefix> {
+    prefixes().iter().find_map(|prefix| {
+        let captures = prefix.captures(line)?;
+        Some(Prefix {
+            pod: captures.name("pod")?.range(),
+            container: captures.name("container")?.range(),
+            end: captures.get(0)?.end(),
+        })
+    })
+}
+
+/// Returns where the message of the line starts, after its prefix if it has one.
+pub fn message_start(line: &str) -> usize {
+    parse(line).map_or(0, |prefix| prefix.end)
+}
+
+impl Prefix {
+    pub fn pod<'a>(&self, line: &'a str) -> &'a str {
+        &line[self.pod.clone()]
+    }
+
+    /// Returns the pod and container as a record, for conditions on them to be tested against.
+    pub fn record(&self, line: &str) -> Value {
+        json!({
+            "pod": self.pod(line),
+            "container": &line[self.container.clone()],
+        })
+    }
+
+    /// Returns where the field is in the line if it is the pod or the container.
+    pub fn locate(&self, key: &str) -> Option<Match> {
+        let range = match key {
+            "pod" => &self.pod,
+            "container" => &self.container,
+            _ => return None,
+        };
+        Some(Match::new(range.start, range.end))
+    }
+}
+
+/// Returns the style of the pod, the same for all of its lines.
+pub fn style(pod: &str) -> ContentStyle {
+    // FNV-1a, which unlike the hasher of the standard library is the same across runs.
+    let hash = pod.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    ContentStyle {
+        foreground_color: Some(COLORS[(hash % COLORS.len() as u64) as usize]),
+        ..Default::default()
+    }
+}
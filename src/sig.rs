@@ -1,4 +1,8 @@
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::Arc,
+};
 
 use grep::{
     matcher::{Match, Matcher},
@@ -12,14 +16,143 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use promkit::{
-    crossterm::{self, event, style::ContentStyle},
+    crossterm::{
+        self,
+        event::{self, Event, MouseEvent, MouseEventKind},
+        style::ContentStyle,
+    },
     grapheme::StyledGraphemes,
+    pane::Pane,
     switch::ActiveKeySwitcher,
     text_editor, PaneFactory,
 };
 
 mod keymap;
-use crate::{cmd, stdin, terminal::Terminal, Signal};
+use crate::{
+    cmd,
+    dedupe::Dedupe,
+    editing::Undo,
+    extract::Extractor,
+    gutter::{self, Gutter, Line},
+    help::{Help, Settings},
+    history, json,
+    keybinding::{ModeKeybindings, Preset},
+    layout::Layout,
+    level::{Level, Levels},
+    logfmt, pod,
+    stats::Stats,
+    stdin,
+    terminal::{self, Height, Terminal},
+    timestamp,
+    vi::{self, Vi},
+    Signal,
+};
+
+/// State of the prompt in streaming mode, apart from the query shared with the renderer.
+pub struct Streaming {
+    keymap: ActiveKeySwitcher<keymap::Keymap>,
+    keybindings: ModeKeybindings<keymap::Action, keymap::PromptAction>,
+    search: Option<history::Search>,
+    vi: Vi,
+    /// Text deleted last, for yanking.
+    killed: String,
+    undo: Undo,
+    /// Overlay listing the key bindings, shown in place of the query while open.
+    help: Option<Help>,
+    /// Whether sig owns the screen, so that the lines can be scrolled.
+    alternate_screen: bool,
+    /// Rows to scroll the lines back (or forth if negative) before drawing next.
+    scroll: isize,
+    layout: Layout,
+    levels: Levels,
+    /// Whether the counts of the matches are shown above the query.
+    stats: bool,
+    /// Whether only what the query captures is shown of the lines, if it has capture groups.
+    only_captures: bool,
+    case_insensitive: bool,
+    /// How the repeats of lines are collapsed as they arrive.
+    dedupe: Option<Dedupe>,
+    cmd: Option<String>,
+}
+
+impl Streaming {
+    fn open_help(&mut self) {
+        let mut help = Help::new("streaming")
+            .settings(Settings {
+                case_insensitive: self.case_insensitive,
+                levels: &self.levels,
+                truncate: Some(self.layout.truncate),
+                only_captures: self.only_captures,
+                stats: self.stats,
+                dedupe: self.dedupe,
+                hidden_pods: None,
+                time_range: None,
+                normal: self.vi.is_normal(),
+            })
+            .keybindings("Keys", &self.keybindings.query)
+            .keybindings(
+                "Keys in the help and history search",
+                &self.keybindings.prompts,
+            );
+        if self.vi.is_normal() {
+            help = help.section("Keys in normal mode", vi::COMMANDS.iter().copied());
+        }
+        self.help = Some(help);
+        self.keymap.switch("help");
+    }
+
+    fn close_help(&mut self) {
+        self.help = None;
+        self.keymap.switch(self.vi.keymap());
+    }
+
+    /// Scrolls the lines back (or forth if negative) by the given rows,
+    /// which only sig's own screen can do.
+    fn scroll(&mut self, rows: isize) {
+        if self.alternate_screen {
+            self.scroll += rows;
+        }
+    }
+
+    /// Scrolls the lines back (or forth if negative) by the given pages.
+    fn scroll_pages(&mut self, pages: isize) -> anyhow::Result<()> {
+        let height = crossterm::terminal::size()?.1;
+        // Keep a row of the previous page in view, leaving one for the prompt.
+        self.scroll(pages * height.saturating_sub(2).max(1) as isize);
+        Ok(())
+    }
+}
+
+/// Returns the help if it is open, or else the query, to show below the lines.
+fn prompt_pane(
+    text_editor: &text_editor::State,
+    help: &Option<Help>,
+    stats: &Option<Stats>,
+    bar_style: ContentStyle,
+    size: (u16, u16),
+) -> Pane {
+    match (help, stats) {
+        // Leave a row for the lines streaming above.
+        (Some(help), _) => help.create_pane(size.0, size.1.saturating_sub(1)),
+        (None, Some(stats)) => {
+            let mut rows = stats.rows(size.0, bar_style);
+            let prompt = text_editor.create_pane(size.0, size.1);
+            rows.extend(prompt.extract(prompt.visible_row_count()));
+            Pane::new(rows, 0)
+        }
+        (None, None) => text_editor.create_pane(size.0, size.1),
+    }
+}
+
+/// Returns the bindings of the preset with the given actions rebound.
+pub fn keybindings(
+    preset: Preset,
+    overrides: &HashMap<String, Vec<String>>,
+) -> anyhow::Result<ModeKeybindings<keymap::Action, keymap::PromptAction>> {
+    keymap::keybindings(preset)
+        .configure(overrides)
+        .map_err(|e| anyhow::anyhow!("invalid streaming keybindings: {}", e))
+}
 
 fn matched(queries: &[&str], line: &str, case_insensitive: bool) -> anyhow::Result<Vec<Match>> {
     let mut matched = Vec::new();
@@ -36,61 +169,332 @@ fn matched(queries: &[&str], line: &str, case_insensitive: bool) -> anyhow::Resu
     Ok(matched)
 }
 
+/// Returns the matches of the query in the line,
+/// or `None` if the line should be filtered out.
+///
+/// Alternatives written as conditions on fields (e.g. `level=error`) are tested
+/// against the fields of JSON or logfmt records having them, and the others
+/// (or those of lines without such a field) are regular expressions.
+pub fn find(query: &str, line: &str, case_insensitive: bool) -> Option<Vec<Match>> {
+    if query.is_empty() {
+        return Some(vec![]);
+    }
+
+    let mut patterns = Vec::new();
+    let mut conditions = Vec::new();
+    for alternative in query.split('|').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        match json::Condition::parse(alternative) {
+            Some(condition) => conditions.push((alternative, condition)),
+            None => patterns.push(alternative),
+        }
+    }
+
+    let mut matches = Vec::new();
+    let mut satisfied = false;
+    // The pod and container of stern or `kubectl logs --prefix` are fields of their own,
+    // and the message after them is read as a record, as JSON first, then as logfmt.
+    let prefix = pod::parse(line);
+    let start = prefix.as_ref().map_or(0, |prefix| prefix.end);
+    let message = &line[start..];
+    let record = if conditions.is_empty() {
+        None
+    } else if let Some(record) = json::record(message) {
+        Some((record, None))
+    } else {
+        logfmt::parse_from(line, start).map(|fields| (logfmt::record(line, &fields), Some(fields)))
+    };
+    let prefix_record = prefix.as_ref().map(|prefix| prefix.record(line));
+    for (alternative, condition) in conditions {
+        let key = condition.key();
+        let (record, found) = match (&prefix, &prefix_record, &record) {
+            (Some(prefix), Some(prefix_record), _) if prefix.locate(&key).is_some() => {
+                (prefix_record, prefix.locate(&key))
+            }
+            (_, _, Some((record, Some(fields)))) => (record, logfmt::locate(line, fields, &key)),
+            (_, _, Some((record, None))) => {
+                (record, condition.locate(message).map(|m| m.offset(start)))
+            }
+            // Lines other than records are searched for the conditions as they are.
+            (_, _, None) => {
+                patterns.push(alternative);
+                continue;
+            }
+        };
+        match condition.test(record, case_insensitive) {
+            Some(true) => {
+                satisfied = true;
+                matches.extend(found);
+            }
+            Some(false) => (),
+            // Records without the field are searched for the condition as it is.
+            None => patterns.push(alternative),
+        }
+    }
+    if !patterns.is_empty() {
+        matches.extend(matched(&patterns, line, case_insensitive).unwrap_or_default());
+    }
+
+    if matches.is_empty() && !satisfied {
+        None
+    } else {
+        matches.sort_by_key(|m| m.start());
+        Some(matches)
+    }
+}
+
+pub fn highlight(line: &str, matches: &[Match], highlight_style: ContentStyle) -> StyledGraphemes {
+    highlight_over(StyledGraphemes::from(line), line, matches, highlight_style)
+}
+
+/// Highlights the matches over the line styled already, e.g. with its fields colored.
+fn highlight_over(
+    mut styled: StyledGraphemes,
+    line: &str,
+    matches: &[Match],
+    highlight_style: ContentStyle,
+) -> StyledGraphemes {
+    for m in matches {
+        // Matches are in bytes, whereas graphemes are indexed by chars.
+        let start = line[..m.start()].chars().count();
+        let end = start + line[m.start()..m.end()].chars().count();
+        for i in start..end {
+            styled = styled.apply_style_at(i, highlight_style);
+        }
+    }
+    styled
+}
+
+/// Returns the line styled with its matches highlighted, its logfmt fields decorated,
+/// its pod colored and its level colored, along with the grapheme its first match starts at,
+/// or `None` if it does not match the query or is below the minimum level.
+fn render(
+    query: &str,
+    line: &Line,
+    highlight_style: ContentStyle,
+    fields: &logfmt::Fields,
+    levels: &Levels,
+    case_insensitive: bool,
+) -> Option<(StyledGraphemes, Option<usize>)> {
+    if !levels.admits(line.level()) {
+        return None;
+    }
+    let (level, line) = (&line.level, line.text.as_str());
+    let matches = find(query, line, case_insensitive)?;
+    let first_match = matches.first().map(|m| line[..m.start()].chars().count());
+
+    let prefix = pod::parse(line);
+    let parsed = logfmt::parse_from(line, prefix.as_ref().map_or(0, |prefix| prefix.end));
+    let mut styled = match &parsed {
+        Some(parsed) => fields.colored(line, parsed),
+        None => StyledGraphemes::from(line),
+    };
+    if let Some(prefix) = &prefix {
+        let span = Match::new(prefix.pod.start, prefix.container.end);
+        styled = highlight_over(styled, line, &[span], pod::style(prefix.pod(line)));
+    }
+    if levels.colored {
+        if let Some((level, span)) = level {
+            let span = Match::new(span.start, span.end);
+            styled = highlight_over(styled, line, &[span], levels.style(*level));
+        }
+    }
+    let styled = highlight_over(styled, line, &matches, highlight_style);
+    Some(match parsed {
+        Some(parsed) => fields.hide(line, &parsed, styled, first_match),
+        None => (styled, first_match),
+    })
+}
+
 pub fn styled(
     query: &str,
-    line: &str,
+    line: &Line,
     highlight_style: ContentStyle,
+    fields: &logfmt::Fields,
+    levels: &Levels,
     case_insensitive: bool,
 ) -> Option<StyledGraphemes> {
-    let piped = &query
-        .split('|')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<&str>>();
+    render(
+        query,
+        line,
+        highlight_style,
+        fields,
+        levels,
+        case_insensitive,
+    )
+    .map(|(styled, _)| styled)
+}
 
-    let mut styled = StyledGraphemes::from(line);
+/// How the lines are laid out and styled for the query and the settings in effect.
+struct Renderer<'a> {
+    query: &'a str,
+    highlight_style: ContentStyle,
+    fields: &'a logfmt::Fields,
+    levels: &'a Levels,
+    case_insensitive: bool,
+    /// With an extractor, only what the line captures is shown, if it captures anything.
+    extractor: Option<&'a Extractor>,
+    layout: &'a Layout,
+    gutter: &'a Gutter,
+}
 
-    if query.is_empty() {
-        Some(styled)
-    } else {
-        match matched(piped, line, case_insensitive) {
-            Ok(matches) => {
-                if matches.is_empty() {
-                    None
-                } else {
-                    for m in matches {
-                        for i in m.start()..m.end() {
-                            styled = styled.apply_style_at(i, highlight_style);
-                        }
-                    }
-                    Some(styled)
+impl Renderer<'_> {
+    /// Returns the rows of the line laid out in the size after its gutter,
+    /// or `None` if it does not match the query.
+    fn rows(&self, line: &Line, size: (u16, u16)) -> Option<Vec<StyledGraphemes>> {
+        let rendered = match self.extractor {
+            Some(extractor) => self
+                .levels
+                .admits(line.level())
+                .then(|| extractor.extract(&line.text))
+                .flatten()
+                .map(|captured| (StyledGraphemes::from(captured), None)),
+            None => render(
+                self.query,
+                line,
+                self.highlight_style,
+                self.fields,
+                self.levels,
+                self.case_insensitive,
+            ),
+        };
+        rendered.map(|(styled, first_match)| {
+            let gutter = self.gutter.render(line);
+            let width = size.0.saturating_sub(gutter.widths() as u16).max(1);
+            gutter::prefix(
+                &gutter,
+                self.layout.rows(styled, first_match, (width, size.1)),
+            )
+        })
+    }
+
+    /// Returns the rows of the lines matching the query, laid out in the size,
+    /// as many as fit in the height and ending `scrolled` rows back from the latest,
+    /// along with `scrolled` limited to the rows there are and the rows of the latest line.
+    fn viewport_rows(
+        &self,
+        queue: &VecDeque<Line>,
+        size: (u16, u16),
+        scrolled: usize,
+    ) -> (Vec<StyledGraphemes>, usize, usize) {
+        let height = size.1 as usize;
+        let mut rows = VecDeque::new();
+        let mut latest = 0;
+        for (i, line) in queue.iter().rev().enumerate() {
+            if rows.len() >= height + scrolled {
+                break;
+            }
+            if let Some(matrix) = self.rows(line, size) {
+                if i == 0 {
+                    latest = matrix.len();
+                }
+                for row in matrix.into_iter().rev() {
+                    rows.push_front(row);
                 }
             }
-            _ => None,
         }
+        let scrolled = scrolled.min(rows.len().saturating_sub(height));
+        let end = rows.len() - scrolled;
+        let rows = rows
+            .range(end.saturating_sub(height)..end)
+            .cloned()
+            .collect();
+        (rows, scrolled, latest)
     }
 }
 
+/// Options of streaming mode, mostly as given on the command line.
+#[derive(Clone)]
+pub struct Options {
+    pub highlight_style: ContentStyle,
+    pub fields: logfmt::Fields,
+    pub levels: Levels,
+    pub retrieval_timeout: Duration,
+    pub render_interval: Duration,
+    pub queue_capacity: usize,
+    pub dedupe: Option<Dedupe>,
+    pub only_captures: bool,
+    pub case_insensitive: bool,
+    pub alternate_screen: bool,
+    /// Rows at the bottom of the terminal to draw in, or `None` for all of them.
+    pub height: Option<Height>,
+    pub truncate: bool,
+    pub gutter: Gutter,
+    pub timestamps: timestamp::Parser,
+    pub keybindings: ModeKeybindings<keymap::Action, keymap::PromptAction>,
+    pub cmd: Option<String>,
+}
+
 pub async fn run(
     text_editor: text_editor::State,
-    highlight_style: ContentStyle,
-    retrieval_timeout: Duration,
-    render_interval: Duration,
-    queue_capacity: usize,
-    case_insensitive: bool,
-    cmd: Option<String>,
-) -> anyhow::Result<(Signal, VecDeque<String>)> {
-    let keymap = ActiveKeySwitcher::new("default", keymap::default);
-    let size = crossterm::terminal::size()?;
+    options: Options,
+) -> anyhow::Result<(Signal, VecDeque<Line>)> {
+    let Options {
+        highlight_style,
+        fields,
+        levels,
+        retrieval_timeout,
+        render_interval,
+        queue_capacity,
+        dedupe,
+        only_captures,
+        case_insensitive,
+        alternate_screen,
+        height,
+        truncate,
+        gutter,
+        timestamps,
+        keybindings,
+        cmd,
+    } = options;
+    let mut prompt = Streaming {
+        keymap: ActiveKeySwitcher::new("default", keymap::default as keymap::Keymap)
+            .register("normal", keymap::normal)
+            .register("search", keymap::search)
+            .register("help", keymap::help),
+        keybindings,
+        search: None,
+        vi: Vi::default(),
+        killed: String::new(),
+        undo: Undo::new(&text_editor.texteditor.text_without_cursor().to_string()),
+        help: None,
+        alternate_screen,
+        scroll: 0,
+        layout: Layout {
+            truncate,
+            hscroll: 0,
+        },
+        levels,
+        stats: false,
+        only_captures,
+        case_insensitive,
+        dedupe,
+        cmd: cmd.clone(),
+    };
+    if alternate_screen {
+        crossterm::execute!(io::stdout(), event::EnableMouseCapture)?;
+    }
+    let size = terminal::size(height)?;
 
     let pane = text_editor.create_pane(size.0, size.1);
-    let mut term = Terminal::new(&pane)?;
+    let mut term = Terminal::new(&pane, height)?;
     term.draw_pane(&pane)?;
 
     let shared_term = Arc::new(RwLock::new(term));
     let shared_text_editor = Arc::new(RwLock::new(text_editor));
-    let readonly_term = Arc::clone(&shared_term);
+    let writable_term = Arc::clone(&shared_term);
     let readonly_text_editor = Arc::clone(&shared_text_editor);
+    let shared_layout = Arc::new(RwLock::new(prompt.layout));
+    let readonly_layout = Arc::clone(&shared_layout);
+    let shared_levels = Arc::new(RwLock::new(prompt.levels));
+    let readonly_levels = Arc::clone(&shared_levels);
+    let shared_only_captures = Arc::new(RwLock::new(prompt.only_captures));
+    let readonly_only_captures = Arc::clone(&shared_only_captures);
+    let shared_help = Arc::new(RwLock::new(None));
+    let readonly_help = Arc::clone(&shared_help);
+    let shared_stats: Arc<RwLock<Option<Stats>>> = Arc::new(RwLock::new(None));
+    let writable_stats = Arc::clone(&shared_stats);
+    let shared_queue = Arc::new(RwLock::new(VecDeque::<Line>::with_capacity(queue_capacity)));
+    let writable_queue = Arc::clone(&shared_queue);
 
     let (tx, mut rx) = mpsc::channel(1);
     let canceler = CancellationToken::new();
@@ -102,60 +506,204 @@ pub async fn run(
         tokio::spawn(async move { stdin::streaming(tx, retrieval_timeout, canceled).await })
     };
 
-    let keeping: JoinHandle<anyhow::Result<VecDeque<String>>> = tokio::spawn(async move {
-        let mut queue = VecDeque::with_capacity(queue_capacity);
+    let rendered_fields = fields.clone();
+    let rendered_gutter = gutter.clone();
+    let sampled_timestamps = timestamps.clone();
+    let keeping: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
         let interval = time::interval(render_interval);
         futures::pin_mut!(interval);
+        let mut number = 0;
+        // Compiled again only when the query changes, keeping the query even if it has no groups.
+        let mut extractor: Option<(String, Option<Extractor>)> = None;
+        // Whether the last line passed the query and the minimum level it was filtered by,
+        // so that its duplicates are counted on it only while they fare the same.
+        let mut last_passed: Option<(String, Option<Level>, bool)> = None;
 
         loop {
             interval.tick().await;
             match rx.recv().await {
-                Some(line) => {
+                Some(text) => {
+                    number += 1;
+                    let line = Line::new(number, text);
                     let text_editor = readonly_text_editor.read().await;
-                    let size = crossterm::terminal::size()?;
-
-                    if queue.len() > queue_capacity {
-                        queue.pop_front().unwrap();
+                    let query = text_editor.texteditor.text_without_cursor().to_string();
+                    let size = terminal::size(height)?;
+                    let layout = readonly_layout.read().await;
+                    let levels = readonly_levels.read().await;
+                    if *readonly_only_captures.read().await {
+                        if extractor
+                            .as_ref()
+                            .is_none_or(|(compiled, _)| *compiled != query)
+                        {
+                            extractor =
+                                Some((query.clone(), Extractor::new(&query, case_insensitive)));
+                        }
+                    } else {
+                        extractor = None;
                     }
-                    queue.push_back(line.clone());
-
-                    if let Some(styled) = styled(
-                        &text_editor.texteditor.text_without_cursor().to_string(),
-                        &line,
+                    let renderer = Renderer {
+                        query: &query,
                         highlight_style,
+                        fields: &rendered_fields,
+                        levels: &levels,
                         case_insensitive,
-                    ) {
-                        let matrix = styled.matrixify(size.0 as usize, size.1 as usize, 0).0;
-                        let term = readonly_term.read().await;
-                        term.draw_stream_and_pane(
-                            matrix,
-                            &text_editor.create_pane(size.0, size.1),
-                        )?;
+                        extractor: extractor
+                            .as_ref()
+                            .and_then(|(_, extractor)| extractor.as_ref()),
+                        layout: &layout,
+                        gutter: &rendered_gutter,
+                    };
+                    let rows = |line: &Line| renderer.rows(line, size);
+
+                    let mut matrix = rows(&line);
+                    let passed = Some((query.clone(), levels.minimum, matrix.is_some()));
+                    let mut queue = writable_queue.write().await;
+                    // A duplicate of the last line is counted on it, which is drawn again in place,
+                    // unless one passes the current query or level and the other did not.
+                    let (line, duplicate) = match queue.back_mut() {
+                        Some(last)
+                            if dedupe.is_some_and(|d| d.same(&last.text, &line.text))
+                                && last_passed == passed =>
+                        {
+                            last.repeat(line);
+                            if matrix.is_some() {
+                                matrix = rows(last);
+                            }
+                            (last.clone(), true)
+                        }
+                        _ => {
+                            last_passed = passed;
+                            if queue.len() > queue_capacity {
+                                queue.pop_front().unwrap();
+                            }
+                            queue.push_back(line.clone());
+                            (line, false)
+                        }
+                    };
+                    drop(queue);
+
+                    let help = readonly_help.read().await;
+                    let mut stats = writable_stats.write().await;
+                    if let Some(stats) = stats.as_mut() {
+                        let time = sampled_timestamps.parse(&line.text).unwrap_or(line.arrived);
+                        stats.push(&line.text, Some(time));
+                    }
+                    let pane = prompt_pane(&text_editor, &help, &stats, highlight_style, size);
+                    let mut term = writable_term.write().await;
+                    match matrix {
+                        Some(matrix) => term.draw_stream_and_pane(duplicate, matrix, &pane)?,
+                        None => {
+                            // A duplicate not drawn leaves the rows of the one before as they are.
+                            if !duplicate {
+                                term.skip();
+                            }
+                            // The stats change with every line, matching or not.
+                            if stats.is_some() {
+                                term.draw_pane(&pane)?;
+                            }
+                        }
                     }
                 }
                 None => break,
             }
         }
-        Ok(queue)
+        Ok(())
     });
 
     let mut signal: Signal;
     loop {
         let event = event::read()?;
+        // Only the wheel is of interest among the mouse events.
+        if let Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Moved | MouseEventKind::Drag(_),
+            ..
+        }) = event
+        {
+            continue;
+        }
         let mut text_editor = shared_text_editor.write().await;
-        signal = keymap.get()(&event, &mut text_editor, cmd.clone())?;
+        let position = text_editor.texteditor.position();
+        let keymap = *prompt.keymap.get();
+        signal = keymap(&event, &mut prompt, &mut text_editor)?;
+        if prompt.undo.changed(&text_editor) {
+            prompt.undo.record(&text_editor, position);
+        }
         if signal == Signal::GotoArchived || signal == Signal::GotoStreaming {
             break;
         }
 
-        let size = crossterm::terminal::size()?;
-        let pane = text_editor.create_pane(size.0, size.1);
+        let mut layout = shared_layout.write().await;
+        let relayout = *layout != prompt.layout;
+        *layout = prompt.layout;
+        // Like the query, the minimum level applies to the lines arriving from now on.
+        let mut levels = shared_levels.write().await;
+        *levels = prompt.levels;
+        // Unlike the minimum level, showing only the captures applies to the lines shown.
+        let mut only_captures = shared_only_captures.write().await;
+        let recapture = *only_captures != prompt.only_captures;
+        *only_captures = prompt.only_captures;
+        let mut help = shared_help.write().await;
+        help.clone_from(&prompt.help);
+        // Unlike the lines, the stats are counted again over the queue for a new query.
+        let query = text_editor.texteditor.text_without_cursor().to_string();
+        let mut stats = shared_stats.write().await;
+        if prompt.stats != stats.is_some()
+            || stats.as_ref().is_some_and(|stats| stats.query != query)
+        {
+            *stats = None;
+            if prompt.stats {
+                let mut counted = Stats::new(&query, case_insensitive, queue_capacity);
+                for line in shared_queue.read().await.iter() {
+                    let time = timestamps.parse(&line.text).unwrap_or(line.arrived);
+                    for _ in 0..line.repeats {
+                        counted.push(&line.text, Some(time));
+                    }
+                }
+                *stats = Some(counted);
+            }
+        }
+
+        let size = terminal::size(height)?;
+        let pane = prompt_pane(&text_editor, &help, &stats, highlight_style, size);
         let mut term = shared_term.write().await;
-        term.draw_pane(&pane)?;
+        let scroll = std::mem::take(&mut prompt.scroll);
+        // Lines drawn for the previous size or layout are laid out differently now.
+        if scroll != 0 || relayout || recapture || matches!(event, Event::Resize(..)) {
+            let queue = shared_queue.read().await;
+            let extractor = only_captures
+                .then(|| Extractor::new(&query, case_insensitive))
+                .flatten();
+            let renderer = Renderer {
+                query: &query,
+                highlight_style,
+                fields: &fields,
+                levels: &levels,
+                case_insensitive,
+                extractor: extractor.as_ref(),
+                layout: &layout,
+                gutter: &gutter,
+            };
+            let (rows, scrolled, latest) = renderer.viewport_rows(
+                &queue,
+                (
+                    size.0,
+                    size.1.saturating_sub(pane.visible_row_count() as u16),
+                ),
+                term.scrolled().saturating_add_signed(scroll),
+            );
+            term.redraw(&rows, scrolled, latest, &pane)?;
+        } else {
+            term.draw_pane(&pane)?;
+        }
     }
 
     canceler.cancel();
     let _: anyhow::Result<(), anyhow::Error> = streaming.await?;
+    keeping.await??;
+    if alternate_screen {
+        crossterm::execute!(io::stdout(), event::DisableMouseCapture)?;
+    }
 
-    Ok((signal, keeping.await??))
+    let queue = std::mem::take(&mut *shared_queue.write().await);
+    Ok((signal, queue))
 }
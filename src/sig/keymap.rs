@@ -1,86 +1,350 @@
 use promkit::{
-    crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers},
+    crossterm::{
+        self,
+        event::{
+            Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, MouseEvent,
+            MouseEventKind,
+        },
+    },
     text_editor,
 };
 
-use crate::Signal;
+use super::Streaming;
+use crate::{
+    editing, history,
+    keybinding::{KeyChord, Keybindings, ModeKeybindings, Preset},
+    Signal,
+};
+
+pub type Keymap = fn(&Event, &mut Streaming, &mut text_editor::State) -> anyhow::Result<Signal>;
+
+/// Rows the lines are scrolled by a turn of the mouse wheel.
+const WHEEL_ROWS: isize = 3;
+
+/// Actions which can be bound to keys in streaming mode.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Action {
+    /// Exit `sig`
+    Exit,
+    /// Retry the command (only with `--cmd`)
+    Retry,
+    /// Enter archived mode
+    GotoArchived,
+    /// Save the query to the history
+    CommitQuery,
+    /// Recall the previous query from the history
+    HistoryPrevious,
+    /// Recall the next query from the history
+    HistoryNext,
+    /// Search the history
+    SearchHistory,
+    /// Scroll the lines back by a page (only with `--alternate-screen`)
+    ScrollUp,
+    /// Scroll the lines forth by a page, following new lines at the bottom
+    ScrollDown,
+    /// Switch between wrapping long lines and truncating them
+    ToggleWrap,
+    /// Scroll truncated lines to the left
+    ScrollLeft,
+    /// Scroll truncated lines to the right
+    ScrollRight,
+    /// Raise the minimum level of the lines shown, back to all after error
+    CycleLevel,
+    /// Show or hide the counts of the matches over time and of the captured values
+    ToggleStats,
+    /// Show only what the capture groups of the query capture, or the whole lines again
+    ToggleCaptures,
+    /// Move the cursor one character to the left
+    Backward,
+    /// Move the cursor one character to the right
+    Forward,
+    /// Move the cursor to the start of the query
+    MoveToHead,
+    /// Move the cursor to the end of the query
+    MoveToTail,
+    /// Move the cursor to the previous word
+    WordBackward,
+    /// Move the cursor to the next word
+    WordForward,
+    /// Delete the character before the cursor
+    Erase,
+    /// Delete the whole query
+    EraseAll,
+    /// Delete the character at the cursor
+    Delete,
+    /// Delete the word before the cursor, keeping it for yanking
+    EraseWord,
+    /// Delete the word after the cursor, keeping it for yanking
+    EraseNextWord,
+    /// Delete from the cursor to the end, keeping it for yanking
+    KillToEnd,
+    /// Insert the text deleted last
+    Yank,
+    /// Undo the last change to the query
+    Undo,
+    /// Redo the change undone last
+    Redo,
+    /// Switch to vi-style normal mode
+    NormalMode,
+    /// Show the key bindings and settings
+    Help,
+}
+
+/// Actions which can be bound to keys in the prompts of streaming mode,
+/// sharing the keys of the query's actions (e.g. `exit`) where they do the same.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PromptAction {
+    /// Use the query found by the history search
+    Confirm,
+    /// Cancel the history search and restore the query
+    Cancel,
+    /// Scroll the help up
+    Previous,
+    /// Scroll the help down
+    Next,
+}
+
+pub fn keybindings(preset: Preset) -> ModeKeybindings<Action, PromptAction> {
+    let common = [
+        (KeyChord::ctrl('c'), Action::Exit),
+        (KeyChord::ctrl('r'), Action::Retry),
+        (KeyChord::ctrl('f'), Action::GotoArchived),
+        (KeyChord::key(KeyCode::Enter), Action::CommitQuery),
+        (KeyChord::key(KeyCode::Up), Action::HistoryPrevious),
+        (KeyChord::key(KeyCode::Down), Action::HistoryNext),
+        (KeyChord::ctrl('s'), Action::SearchHistory),
+        (KeyChord::key(KeyCode::PageUp), Action::ScrollUp),
+        (KeyChord::key(KeyCode::PageDown), Action::ScrollDown),
+        (KeyChord::alt('w'), Action::ToggleWrap),
+        (KeyChord::shift(KeyCode::Left), Action::ScrollLeft),
+        (KeyChord::shift(KeyCode::Right), Action::ScrollRight),
+        (KeyChord::alt('l'), Action::CycleLevel),
+        (KeyChord::alt('s'), Action::ToggleStats),
+        (KeyChord::alt('o'), Action::ToggleCaptures),
+        (KeyChord::key(KeyCode::Left), Action::Backward),
+        (KeyChord::key(KeyCode::Right), Action::Forward),
+        (KeyChord::alt('b'), Action::WordBackward),
+        (KeyChord::alt('f'), Action::WordForward),
+        (KeyChord::key(KeyCode::Backspace), Action::Erase),
+        (KeyChord::ctrl('u'), Action::EraseAll),
+        (KeyChord::key(KeyCode::Delete), Action::Delete),
+        (KeyChord::ctrl('w'), Action::EraseWord),
+        (KeyChord::alt('d'), Action::EraseNextWord),
+        (KeyChord::ctrl('k'), Action::KillToEnd),
+        (KeyChord::ctrl('y'), Action::Yank),
+        (KeyChord::ctrl('z'), Action::Undo),
+        (KeyChord::alt('z'), Action::Redo),
+        (KeyChord::key(KeyCode::F(1)), Action::Help),
+    ];
+    let editing = match preset {
+        Preset::Emacs => vec![
+            (KeyChord::ctrl('a'), Action::MoveToHead),
+            (KeyChord::ctrl('e'), Action::MoveToTail),
+        ],
+        Preset::Vi => vec![
+            (KeyChord::key(KeyCode::Home), Action::MoveToHead),
+            (KeyChord::key(KeyCode::End), Action::MoveToTail),
+            (KeyChord::key(KeyCode::Esc), Action::NormalMode),
+        ],
+    };
+    let prompts = [
+        (KeyChord::key(KeyCode::Enter), PromptAction::Confirm),
+        (KeyChord::key(KeyCode::Esc), PromptAction::Cancel),
+        (KeyChord::key(KeyCode::Up), PromptAction::Previous),
+        (KeyChord::key(KeyCode::Down), PromptAction::Next),
+    ];
+    ModeKeybindings {
+        query: Keybindings::new(common.into_iter().chain(editing)),
+        prompts: Keybindings::new(prompts),
+    }
+}
+
+fn act(
+    action: Action,
+    streaming: &mut Streaming,
+    state: &mut text_editor::State,
+) -> anyhow::Result<Signal> {
+    match action {
+        Action::Exit => {
+            history::commit(state);
+            return Err(anyhow::anyhow!("ctrl+c"));
+        }
+        Action::Retry if streaming.cmd.is_some() => {
+            history::commit(state);
+            return Ok(Signal::GotoStreaming);
+        }
+        Action::Retry => (),
+        Action::GotoArchived => {
+            history::commit(state);
+            return Ok(Signal::GotoArchived);
+        }
+
+        // History.
+        Action::CommitQuery => history::commit(state),
+        Action::HistoryPrevious => history::backward(state),
+        Action::HistoryNext => history::forward(state),
+        Action::SearchHistory => {
+            streaming.search = Some(history::Search::start(state));
+            streaming.keymap.switch("search");
+        }
+
+        // Scroll lines.
+        Action::ScrollUp => streaming.scroll_pages(1)?,
+        Action::ScrollDown => streaming.scroll_pages(-1)?,
+        Action::ToggleWrap => streaming.layout.toggle_wrap(),
+        Action::ScrollLeft => streaming
+            .layout
+            .scroll(false, crossterm::terminal::size()?.0),
+        Action::ScrollRight => streaming
+            .layout
+            .scroll(true, crossterm::terminal::size()?.0),
+        Action::CycleLevel => streaming.levels.cycle_minimum(),
+        Action::ToggleStats => streaming.stats = !streaming.stats,
+        Action::ToggleCaptures => streaming.only_captures = !streaming.only_captures,
+
+        // Move cursor.
+        Action::Backward => {
+            state.texteditor.backward();
+        }
+        Action::Forward => {
+            state.texteditor.forward();
+        }
+        Action::MoveToHead => state.texteditor.move_to_head(),
+        Action::MoveToTail => state.texteditor.move_to_tail(),
+        Action::WordBackward => state
+            .texteditor
+            .move_to_previous_nearest(&state.word_break_chars),
+        Action::WordForward => state
+            .texteditor
+            .move_to_next_nearest(&state.word_break_chars),
+
+        // Erase char(s).
+        Action::Erase => state.texteditor.erase(),
+        Action::EraseAll => state.texteditor.erase_all(),
+        Action::Delete => editing::delete(state),
+        Action::EraseWord => {
+            streaming.killed = editing::kill(state, |state| {
+                state
+                    .texteditor
+                    .erase_to_previous_nearest(&state.word_break_chars)
+            })
+        }
+        Action::EraseNextWord => {
+            streaming.killed = editing::kill(state, |state| {
+                state
+                    .texteditor
+                    .erase_to_next_nearest(&state.word_break_chars)
+            })
+        }
+        Action::KillToEnd => streaming.killed = editing::kill(state, editing::erase_to_end),
+        Action::Yank => editing::yank(state, &streaming.killed),
+
+        Action::Undo => {
+            streaming.undo.undo(state);
+        }
+        Action::Redo => {
+            streaming.undo.redo(state);
+        }
+
+        Action::NormalMode => {
+            streaming.vi.enter_normal(state);
+            streaming.keymap.switch("normal");
+        }
+        Action::Help => streaming.open_help(),
+    }
+    Ok(Signal::Continue)
+}
 
 pub fn default(
     event: &Event,
+    streaming: &mut Streaming,
     state: &mut text_editor::State,
-    cmd: Option<String>,
 ) -> anyhow::Result<Signal> {
+    if let Some(action) = streaming.keybindings.query.get(event) {
+        return act(action, streaming, state);
+    }
+
     match event {
+        // A query cannot start with `?`, so it shows the help instead.
         Event::Key(KeyEvent {
-            code: KeyCode::Char('f'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char('?'),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => return Ok(Signal::GotoArchived),
+        }) if state.texteditor.text_without_cursor().is_empty() => streaming.open_help(),
 
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollUp,
+            ..
+        }) => streaming.scroll(WHEEL_ROWS),
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollDown,
+            ..
+        }) => streaming.scroll(-WHEEL_ROWS),
+
+        // Input char.
         Event::Key(KeyEvent {
-            code: KeyCode::Char('r'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => {
-            if cmd.is_some() {
-                return Ok(Signal::GotoStreaming);
-            }
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('c'),
-            modifiers: KeyModifiers::CONTROL,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => return Err(anyhow::anyhow!("ctrl+c")),
+        }) => match state.edit_mode {
+            text_editor::Mode::Insert => state.texteditor.insert(*ch),
+            text_editor::Mode::Overwrite => state.texteditor.overwrite(*ch),
+        },
 
-        // Move cursor.
+        _ => (),
+    }
+    Ok(Signal::Continue)
+}
+
+pub fn normal(
+    event: &Event,
+    streaming: &mut Streaming,
+    state: &mut text_editor::State,
+) -> anyhow::Result<Signal> {
+    match event {
         Event::Key(KeyEvent {
-            code: KeyCode::Left,
+            code: KeyCode::Esc,
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => {
-            state.texteditor.backward();
-        }
+        }) => streaming.vi.cancel(),
+
         Event::Key(KeyEvent {
-            code: KeyCode::Right,
+            code: KeyCode::Char('u'),
             modifiers: KeyModifiers::NONE,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
         }) => {
-            state.texteditor.forward();
+            streaming.vi.cancel();
+            return act(Action::Undo, streaming, state);
         }
         Event::Key(KeyEvent {
-            code: KeyCode::Char('a'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }) => state.texteditor.move_to_head(),
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('e'),
-            modifiers: KeyModifiers::CONTROL,
+            code: KeyCode::Char('?'),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => state.texteditor.move_to_tail(),
+        }) => {
+            streaming.vi.cancel();
+            streaming.open_help();
+        }
 
-        // Erase char(s).
-        Event::Key(KeyEvent {
-            code: KeyCode::Backspace,
-            modifiers: KeyModifiers::NONE,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }) => state.texteditor.erase(),
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('u'),
-            modifiers: KeyModifiers::CONTROL,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }) => state.texteditor.erase_all(),
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollUp,
+            ..
+        }) => streaming.scroll(WHEEL_ROWS),
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollDown,
+            ..
+        }) => streaming.scroll(-WHEEL_ROWS),
 
-        // Input char.
         Event::Key(KeyEvent {
             code: KeyCode::Char(ch),
             modifiers: KeyModifiers::NONE,
@@ -92,12 +356,98 @@ pub fn default(
             modifiers: KeyModifiers::SHIFT,
             kind: KeyEventKind::Press,
             state: KeyEventState::NONE,
-        }) => match state.edit_mode {
-            text_editor::Mode::Insert => state.texteditor.insert(*ch),
-            text_editor::Mode::Overwrite => state.texteditor.overwrite(*ch),
-        },
+        }) if streaming.vi.command(state, *ch) => streaming.keymap.switch(streaming.vi.keymap()),
 
-        _ => (),
+        // Keys other than the commands work as in insert mode, except for typing.
+        _ => {
+            if let Some(action) = streaming.keybindings.query.get(event) {
+                return act(action, streaming, state);
+            }
+        }
+    }
+    Ok(Signal::Continue)
+}
+
+pub fn help(
+    event: &Event,
+    streaming: &mut Streaming,
+    _state: &mut text_editor::State,
+) -> anyhow::Result<Signal> {
+    let Some(help) = streaming.help.as_mut() else {
+        return Ok(Signal::Continue);
+    };
+
+    if streaming.keybindings.query.get(event) == Some(Action::Exit) {
+        return Err(anyhow::anyhow!("ctrl+c"));
+    }
+    match streaming
+        .keybindings
+        .prompts
+        .get_among(event, &[PromptAction::Previous, PromptAction::Next])
+    {
+        Some(PromptAction::Previous) => help.scroll_up(),
+        Some(PromptAction::Next) => help.scroll_down(),
+        _ => {
+            if let Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
+                ..
+            }) = event
+            {
+                streaming.close_help();
+            }
+        }
+    }
+    Ok(Signal::Continue)
+}
+
+pub fn search(
+    event: &Event,
+    streaming: &mut Streaming,
+    state: &mut text_editor::State,
+) -> anyhow::Result<Signal> {
+    if let Some(action) = streaming
+        .keybindings
+        .prompts
+        .get_among(event, &[PromptAction::Confirm, PromptAction::Cancel])
+    {
+        if let Some(current) = streaming.search.take() {
+            if action == PromptAction::Confirm {
+                current.accept(state);
+            } else {
+                current.cancel(state);
+            }
+        }
+        streaming.keymap.switch(streaming.vi.keymap());
+        return Ok(Signal::Continue);
+    }
+
+    let Some(current) = streaming.search.as_mut() else {
+        return Ok(Signal::Continue);
+    };
+
+    // Searching again, by whichever keys are bound to it, moves to an older match,
+    // and the keys to exit and erase work as they do on the query.
+    match streaming.keybindings.query.get(event) {
+        Some(Action::SearchHistory) => current.older(state),
+        Some(Action::Exit) => return Err(anyhow::anyhow!("ctrl+c")),
+        Some(Action::Erase) => current.pop(state),
+
+        _ => match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            })
+            | Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                state: KeyEventState::NONE,
+            }) => current.push(state, *ch),
+
+            _ => (),
+        },
     }
     Ok(Signal::Continue)
 }
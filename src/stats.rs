@@ -0,0 +1,172 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, FixedOffset};
+use promkit::{
+    crossterm::style::{Attribute, ContentStyle},
+    grapheme::StyledGraphemes,
+};
+
+use crate::{extract::Extractor, sig};
+
+/// Number of the most common captured values shown.
+const TOP: usize = 5;
+
+/// Bars of the sparkline, from the fewest matches to the most.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Widths of the time buckets in seconds, the smallest of which fitting the width is taken.
+const BUCKETS: [i64; 17] = [
+    1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200, 21600, 43200, 86400,
+];
+
+/// Width of the labels before the sparkline and the top values.
+const LABEL: usize = 9;
+
+/// What the stats keep of a line.
+struct Sample {
+    time: Option<DateTime<FixedOffset>>,
+    matched: bool,
+    /// Value of the capture group, if the line matches and captures one.
+    captured: Option<String>,
+}
+
+/// Counts of the lines matching a query over time, and of the values it captures.
+pub struct Stats {
+    pub query: String,
+    case_insensitive: bool,
+    /// Capture groups of the query, e.g. `status=(\d+)`, whose first match in a line is counted.
+    capture: Option<Extractor>,
+    /// Lines counted at most, the oldest of which are dropped first.
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl Stats {
+    pub fn new(query: &str, case_insensitive: bool, capacity: usize) -> Self {
+        Self {
+            query: query.to_string(),
+            case_insensitive,
+            capture: Extractor::new(query, case_insensitive),
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Counts the line at the time it was logged (or arrived), if known.
+    pub fn push(&mut self, line: &str, time: Option<DateTime<FixedOffset>>) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        // Lines are matched along with their captures, if the query has capture groups.
+        let (matched, captured) = match &self.capture {
+            Some(capture) => match capture.captures(line) {
+                Some(captures) => (true, captures.into_iter().next()),
+                None => (false, None),
+            },
+            None => (
+                sig::find(&self.query, line, self.case_insensitive).is_some(),
+                None,
+            ),
+        };
+        self.samples.push_back(Sample {
+            time,
+            matched,
+            captured,
+        });
+    }
+
+    /// Returns the sparkline of the matches per time bucket with the times it spans below,
+    /// and the most common captured values if the query has a capture group.
+    pub fn rows(&self, width: u16, bar_style: ContentStyle) -> Vec<StyledGraphemes> {
+        let dim = ContentStyle {
+            attributes: Attribute::Dim.into(),
+            ..Default::default()
+        };
+        let label = |text: &str| StyledGraphemes::from_str(format!("{:<LABEL$}", text), dim);
+        let mut rows = vec![];
+
+        let times: Vec<(DateTime<FixedOffset>, bool)> = self
+            .samples
+            .iter()
+            .filter_map(|sample| Some((sample.time?, sample.matched)))
+            .collect();
+        match (times.iter().min(), times.iter().max()) {
+            (Some(&(first, _)), Some(&(last, _))) => {
+                let span = (last - first).num_seconds();
+                let suffix_width = 16;
+                let bars = (width as usize).saturating_sub(LABEL + suffix_width).max(1) as i64;
+                let bucket = BUCKETS
+                    .into_iter()
+                    .find(|bucket| span / bucket < bars)
+                    .unwrap_or(span / bars + 1);
+
+                let mut counts = vec![0; (span / bucket + 1) as usize];
+                for (time, _) in times.iter().filter(|(_, matched)| *matched) {
+                    counts[((*time - first).num_seconds() / bucket) as usize] += 1;
+                }
+                let max = counts.iter().copied().max().unwrap_or(0);
+                let sparkline: String = counts
+                    .iter()
+                    .map(|&count| match count {
+                        0 => ' ',
+                        count => BARS[((count * BARS.len() - 1) / max).min(BARS.len() - 1)],
+                    })
+                    .collect();
+
+                rows.push(StyledGraphemes::from_iter([
+                    label("matches"),
+                    StyledGraphemes::from_str(&sparkline, bar_style),
+                    StyledGraphemes::from_str(
+                        format!("  max {} per {}", max, describe(bucket)),
+                        dim,
+                    ),
+                ]));
+                let (start, end) = (
+                    first.format("%H:%M:%S").to_string(),
+                    last.format("%H:%M:%S").to_string(),
+                );
+                let gap = counts.len().saturating_sub(start.len() + end.len()).max(1);
+                rows.push(StyledGraphemes::from_iter([
+                    label(""),
+                    StyledGraphemes::from_str(format!("{}{}{}", start, " ".repeat(gap), end), dim),
+                ]));
+            }
+            _ => rows.push(StyledGraphemes::from_iter([
+                label("matches"),
+                StyledGraphemes::from_str("(no timestamps in the lines)", dim),
+            ])),
+        }
+
+        if self.capture.is_some() {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for captured in self.samples.iter().filter_map(|s| s.captured.as_deref()) {
+                *counts.entry(captured).or_default() += 1;
+            }
+            let mut top: Vec<(&str, usize)> = counts.into_iter().collect();
+            top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let mut row = label("top");
+            for (i, (value, count)) in top.into_iter().take(TOP).enumerate() {
+                if i > 0 {
+                    row.extend(StyledGraphemes::from("  ").0);
+                }
+                row.extend(StyledGraphemes::from(value).0);
+                row.extend(StyledGraphemes::from_str(format!(" ×{}", count), dim).0);
+            }
+            rows.push(row);
+        }
+
+        rows.into_iter()
+            .flat_map(|row| row.matrixify(width as usize, 1, 0).0.into_iter().take(1))
+            .collect()
+    }
+}
+
+/// Describes the width of a bucket, e.g. `10s` or `5m`.
+fn describe(seconds: i64) -> String {
+    match seconds {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
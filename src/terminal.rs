@@ -1,74 +1,346 @@
-use std::io::{self, Write};
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
 
 use promkit::{
-    crossterm::{self, cursor, style, terminal},
+    crossterm::{self, cursor, event, style, terminal},
     grapheme::StyledGraphemes,
     pane::Pane,
 };
 
+/// Rows at the bottom of the terminal to draw in, given as `20` or `40%`.
+#[derive(Clone, Copy)]
+pub enum Height {
+    Rows(u16),
+    Percent(u16),
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let height = match s.strip_suffix('%') {
+            Some(percent) => percent
+                .parse()
+                .ok()
+                .filter(|p| (1..=100).contains(p))
+                .map(Height::Percent),
+            None => s.parse().ok().filter(|rows| *rows > 0).map(Height::Rows),
+        };
+        height.ok_or_else(|| format!("expected rows (e.g. 20) or a percentage (e.g. 40%): {}", s))
+    }
+}
+
+impl Height {
+    /// Returns the rows out of those of the terminal.
+    pub fn rows(&self, terminal_rows: u16) -> u16 {
+        let rows = match self {
+            Height::Rows(rows) => *rows,
+            Height::Percent(percent) => (terminal_rows as u32 * *percent as u32 / 100) as u16,
+        };
+        rows.clamp(1, terminal_rows.max(1))
+    }
+}
+
+/// Returns the size of the terminal, or of its bottom rows to draw in with `height`.
+pub fn size(height: Option<Height>) -> anyhow::Result<(u16, u16)> {
+    let size = terminal::size()?;
+    Ok((size.0, height.map_or(size.1, |height| height.rows(size.1))))
+}
+
+/// Returns the first row to draw from with `height`.
+fn top(height: Option<Height>) -> anyhow::Result<u16> {
+    let rows = terminal::size()?.1;
+    Ok(rows - height.map_or(rows, |height| height.rows(rows)))
+}
+
+/// Where sig draws in the terminal, restored when dropped.
+pub enum Screen {
+    /// The whole terminal, pushing the output of the shell into its scrollback.
+    Full,
+    /// The alternate screen, leaving the one of the shell as it was.
+    Alternate,
+    /// The bottom rows, keeping the output of the shell visible above.
+    Inline(Height),
+}
+
+impl Screen {
+    pub fn enter(alternate: bool, height: Option<Height>) -> anyhow::Result<Self> {
+        if alternate {
+            crossterm::execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+            return Ok(Screen::Alternate);
+        }
+        let Some(height) = height else {
+            return Ok(Screen::Full);
+        };
+
+        // Scroll the output of the shell up just enough to make room below it.
+        let position = cursor::position()?;
+        let used = position.1 + (position.0 > 0) as u16;
+        let top = top(Some(height))?;
+        if used > top {
+            crossterm::queue!(io::stdout(), terminal::ScrollUp(used - top))?;
+        }
+        let screen = Screen::Inline(height);
+        screen.clear()?;
+        Ok(screen)
+    }
+
+    /// Returns the rows at the bottom to draw in, or `None` for all of them.
+    pub fn height(&self) -> Option<Height> {
+        match self {
+            Screen::Inline(height) => Some(*height),
+            _ => None,
+        }
+    }
+
+    /// Clears where sig draws and moves the cursor to the top, e.g. to switch modes.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        match self {
+            Screen::Full | Screen::Alternate => crossterm::execute!(
+                io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                terminal::Clear(terminal::ClearType::Purge),
+                cursor::MoveTo(0, 0),
+            )?,
+            Screen::Inline(height) => {
+                let top = top(Some(*height))?;
+                crossterm::execute!(
+                    io::stdout(),
+                    cursor::MoveTo(0, top),
+                    terminal::Clear(terminal::ClearType::FromCursorDown),
+                )?
+            }
+        }
+        Ok(())
+    }
+
+    /// Restores the terminal, e.g. to print the lines chosen to it.
+    pub fn leave(&mut self) {
+        // Dropping the screen left restores it.
+        *self = Screen::Full;
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        match self {
+            Screen::Full => (),
+            Screen::Alternate => {
+                crossterm::execute!(
+                    io::stdout(),
+                    event::DisableMouseCapture,
+                    terminal::LeaveAlternateScreen
+                )
+                .ok();
+            }
+            // Leave the cursor where sig was, for the shell to go on from there.
+            Screen::Inline(_) => {
+                self.clear().ok();
+            }
+        }
+    }
+}
+
+/// Sets the rows which scroll (DECSTBM), which crossterm has no command for.
+struct SetScrollRegion(u16, u16);
+
+impl crossterm::Command for SetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{};{}r", self.0 + 1, self.1 + 1)
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "scroll regions need ANSI support",
+        ))
+    }
+}
+
+/// Lets all the rows scroll again.
+struct ResetScrollRegion;
+
+impl crossterm::Command for ResetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[r")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "scroll regions need ANSI support",
+        ))
+    }
+}
+
 pub struct Terminal {
-    anchor_position: (u16, u16),
+    /// Rows at the bottom to draw in, or `None` for all of them.
+    height: Option<Height>,
+    /// First row to draw in.
+    top: u16,
+    /// First row of the pane, below which the streamed lines are drawn.
+    pane_top: u16,
+    /// Rows the lines are scrolled back from the latest, which are followed while it is 0.
+    scrolled: usize,
+    /// Rows the latest line was drawn in, or 0 if it was not,
+    /// for a duplicate of it to be drawn in their place.
+    latest: usize,
 }
 
 impl Terminal {
-    pub fn new(pane: &Pane) -> anyhow::Result<Self> {
-        let mut offset_from_bottom = terminal::size()?;
-        offset_from_bottom.1 = offset_from_bottom
-            .1
-            .saturating_sub(1 + pane.visible_row_count() as u16);
-
+    pub fn new(pane: &Pane, height: Option<Height>) -> anyhow::Result<Self> {
+        let size = terminal::size()?;
         Ok(Self {
-            anchor_position: (0, offset_from_bottom.1),
+            height,
+            top: top(height)?,
+            pane_top: size
+                .1
+                .saturating_sub(pane.visible_row_count() as u16)
+                .max(top(height)?),
+            scrolled: 0,
+            latest: 0,
         })
     }
 
+    pub fn scrolled(&self) -> usize {
+        self.scrolled
+    }
+
+    /// Draws `items` after the lines, or in place of the latest line if `replace`
+    /// (e.g. counting one more duplicate), and the pane below.
     pub fn draw_stream_and_pane(
-        &self,
+        &mut self,
+        replace: bool,
         items: Vec<StyledGraphemes>,
         pane: &Pane,
     ) -> anyhow::Result<()> {
-        let coefficient = items.len().saturating_sub(1) as u16;
-        crossterm::queue!(
-            io::stdout(),
-            cursor::MoveTo(
-                self.anchor_position.0,
-                self.anchor_position.1.saturating_sub(coefficient)
-            ),
-            terminal::ScrollUp(1 + coefficient),
-            terminal::Clear(terminal::ClearType::FromCursorDown),
-        )?;
+        let replaced = if replace { self.latest } else { 0 };
+        self.latest = items.len();
 
-        for item in items.iter() {
+        // Keep the lines in view where they are while scrolled back.
+        if self.scrolled > 0 {
+            self.scrolled = (self.scrolled + items.len()).saturating_sub(replaced);
+            return self.draw(pane);
+        }
+
+        // Only the rows fitting above the pane can be shown.
+        let available = (self.pane_top - self.top) as usize;
+        let items = &items[items.len().saturating_sub(available)..];
+        let replaced = replaced.min(available) as u16;
+        if !items.is_empty() {
+            let count = items.len() as u16;
+            if count != replaced {
+                // Scrolling the whole terminal would take the output of the shell with it.
+                if self.top > 0 {
+                    crossterm::queue!(io::stdout(), SetScrollRegion(self.top, self.pane_top - 1))?;
+                }
+                // The lines before scroll up for the rows added, or down for those gone.
+                if count > replaced {
+                    crossterm::queue!(io::stdout(), terminal::ScrollUp(count - replaced))?;
+                } else {
+                    crossterm::queue!(io::stdout(), terminal::ScrollDown(replaced - count))?;
+                }
+                if self.top > 0 {
+                    crossterm::queue!(io::stdout(), ResetScrollRegion)?;
+                }
+            }
             crossterm::queue!(
                 io::stdout(),
-                style::Print(item.styled_display()),
-                cursor::MoveToNextLine(1)
+                cursor::MoveTo(0, self.pane_top - count),
+                terminal::Clear(terminal::ClearType::FromCursorDown)
             )?;
+
+            for item in items.iter() {
+                crossterm::queue!(
+                    io::stdout(),
+                    style::Print(item.styled_display()),
+                    cursor::MoveToNextLine(1)
+                )?;
+            }
         }
 
         io::stdout().flush()?;
         self.draw(pane)
     }
 
+    /// Notes that the latest line is not drawn, leaving no rows to draw a duplicate over.
+    pub fn skip(&mut self) {
+        self.latest = 0;
+    }
+
     pub fn draw_pane(&mut self, pane: &Pane) -> anyhow::Result<()> {
         let size = terminal::size()?;
         crossterm::queue!(
             io::stdout(),
-            cursor::MoveTo(self.anchor_position.0, self.anchor_position.1 + 1),
+            cursor::MoveTo(0, self.pane_top),
+            terminal::Clear(terminal::ClearType::FromCursorDown),
+        )?;
+        self.pane_top = size
+            .1
+            .saturating_sub(pane.visible_row_count() as u16)
+            .max(self.top);
+        self.draw(pane)
+    }
+
+    /// Draws everything again, e.g. after the terminal is resized or the lines are scrolled,
+    /// with `items` being the rows to show above the pane, `scrolled` rows back from the latest,
+    /// the last `latest` of which are of the latest line.
+    pub fn redraw(
+        &mut self,
+        items: &[StyledGraphemes],
+        scrolled: usize,
+        latest: usize,
+        pane: &Pane,
+    ) -> anyhow::Result<()> {
+        self.latest = latest;
+        let size = terminal::size()?;
+        self.top = top(self.height)?;
+        self.pane_top = size
+            .1
+            .saturating_sub(pane.visible_row_count() as u16)
+            .max(self.top);
+        self.scrolled = scrolled;
+
+        let items = &items[items
+            .len()
+            .saturating_sub((self.pane_top - self.top) as usize)..];
+        crossterm::queue!(
+            io::stdout(),
+            cursor::MoveTo(0, self.top),
             terminal::Clear(terminal::ClearType::FromCursorDown),
+            cursor::MoveTo(0, self.pane_top - items.len() as u16),
         )?;
-        self.anchor_position.1 = size.1.saturating_sub(1 + pane.visible_row_count() as u16);
+        for item in items.iter() {
+            crossterm::queue!(
+                io::stdout(),
+                style::Print(item.styled_display()),
+                cursor::MoveToNextLine(1)
+            )?;
+        }
+
+        io::stdout().flush()?;
         self.draw(pane)
     }
 
     fn draw(&self, pane: &Pane) -> anyhow::Result<()> {
+        let size = terminal::size()?;
         crossterm::queue!(
             io::stdout(),
-            cursor::MoveTo(self.anchor_position.0, self.anchor_position.1 + 1),
+            cursor::MoveTo(0, self.pane_top),
             terminal::Clear(terminal::ClearType::FromCursorDown),
         )?;
 
-        for row in pane.extract(pane.visible_row_count()) {
+        // Rows beyond the bottom would scroll the terminal, so they are left out.
+        let rows = pane.extract(size.1.saturating_sub(self.pane_top) as usize);
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 {
+                crossterm::queue!(io::stdout(), cursor::MoveToNextLine(1))?;
+            }
             crossterm::queue!(io::stdout(), style::Print(row.styled_display()))?;
         }
 
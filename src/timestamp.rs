@@ -0,0 +1,407 @@
+use std::sync::OnceLock;
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike,
+};
+use regex::{Captures, Regex};
+
+use crate::clock;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats of timestamps recognized anywhere in lines, tried in order.
+fn formats() -> &'static [Regex; 4] {
+    static FORMATS: OnceLock<[Regex; 4]> = OnceLock::new();
+    FORMATS.get_or_init(|| {
+        let months = MONTHS.join("|");
+        [
+            // RFC3339 and the like, e.g. `2024-04-12T10:32:00.123Z` or `2024-04-12 10:32:00`.
+            Regex::new(
+                r"(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2}):(\d{2})(?:[.,](\d{1,9}))?(?: ?(Z|[+-]\d{2}:?\d{2}))?",
+            ),
+            // nginx and Apache, e.g. `12/Apr/2024:10:32:00 +0000`.
+            Regex::new(&format!(
+                r"(\d{{2}})/({months})/(\d{{4}}):(\d{{2}}):(\d{{2}}):(\d{{2}}) ([+-]\d{{4}})"
+            )),
+            // klog, e.g. `E0412 10:32:00.123456`, without the year.
+            Regex::new(r"^[IWEF](\d{2})(\d{2}) (\d{2}):(\d{2}):(\d{2})(?:\.(\d{1,9}))?"),
+            // syslog, e.g. `Apr 12 10:32:00`, without the year.
+            Regex::new(&format!(
+                r"\b({months}) {{1,2}}(\d{{1,2}}) (\d{{2}}):(\d{{2}}):(\d{{2}})\b"
+            )),
+        ]
+        .map(Result::unwrap)
+    })
+}
+
+/// Finds the timestamps of lines.
+#[derive(Clone, Default)]
+pub struct Parser {
+    /// strftime format (e.g. `%Y/%m/%d %H:%M:%S`) tried before the known ones.
+    pub format: Option<String>,
+}
+
+impl Parser {
+    /// Returns the first timestamp in the line, taken as local time if it has no offset
+    /// and as of the current year if it has no year.
+    pub fn parse(&self, line: &str) -> Option<DateTime<FixedOffset>> {
+        if let Some(format) = &self.format {
+            if let Some(time) = parse_with(line, format) {
+                return Some(time);
+            }
+        }
+
+        let [rfc3339, nginx, klog, syslog] = formats();
+        if let Some(c) = rfc3339.captures(line) {
+            let date = (number(&c, 1)?, number(&c, 2)?, number(&c, 3)?);
+            let time = naive(&c, date, 4, true)?;
+            return match c.get(8).map(|m| m.as_str()) {
+                Some("Z") => Some(time.and_utc().fixed_offset()),
+                Some(offset) => time.and_local_timezone(parse_offset(offset)?).single(),
+                None => Some(clock::localize(time)),
+            };
+        }
+        if let Some(c) = nginx.captures(line) {
+            let date = (number(&c, 3)?, month(&c[2])?, number(&c, 1)?);
+            let time = naive(&c, date, 4, false)?;
+            return time.and_local_timezone(parse_offset(&c[7])?).single();
+        }
+        let year = clock::now().year() as u32;
+        if let Some(c) = klog.captures(line) {
+            let time = naive(&c, (year, number(&c, 1)?, number(&c, 2)?), 3, true)?;
+            return Some(clock::localize(time));
+        }
+        if let Some(c) = syslog.captures(line) {
+            let time = naive(&c, (year, month(&c[1])?, number(&c, 2)?), 3, false)?;
+            return Some(clock::localize(time));
+        }
+        None
+    }
+}
+
+/// Parses the first timestamp in the format found at the start of a word of the line.
+fn parse_with(line: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    let starts = line
+        .char_indices()
+        .filter(|&(i, _)| {
+            i == 0 || line[..i].ends_with(|c: char| c.is_whitespace() || "[(\"'=".contains(c))
+        })
+        .map(|(i, _)| i);
+    for start in starts {
+        let rest = &line[start..];
+        if let Ok((time, _)) = DateTime::parse_and_remainder(rest, format) {
+            return Some(time);
+        }
+        if let Ok((time, _)) = NaiveDateTime::parse_and_remainder(rest, format) {
+            return Some(clock::localize(time));
+        }
+    }
+    None
+}
+
+fn number(captures: &Captures, group: usize) -> Option<u32> {
+    captures.get(group)?.as_str().parse().ok()
+}
+
+fn month(name: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .position(|month| *month == name)
+        .map(|i| i as u32 + 1)
+}
+
+/// Returns the date with the time captured from the `hour`-th group on:
+/// hours, minutes, seconds and, if `fraction`, a fraction of a second.
+fn naive(
+    captures: &Captures,
+    (year, month, day): (u32, u32, u32),
+    hour: usize,
+    fraction: bool,
+) -> Option<NaiveDateTime> {
+    let fraction = captures
+        .get(hour + 3)
+        .filter(|_| fraction)
+        .map_or("0", |m| m.as_str());
+    let nanos = format!("{:0<9}", fraction).parse().ok()?;
+    NaiveDate::from_ymd_opt(year as i32, month, day)?.and_hms_nano_opt(
+        number(captures, hour)?,
+        number(captures, hour + 1)?,
+        number(captures, hour + 2)?,
+        nanos,
+    )
+}
+
+/// Parses an offset written as `+0900`, `+09:00` or `-05`.
+fn parse_offset(offset: &str) -> Option<FixedOffset> {
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits: String = offset[1..].chars().filter(char::is_ascii_digit).collect();
+    let hours: i32 = digits.get(..2)?.parse().ok()?;
+    let minutes: i32 = digits
+        .get(2..)
+        .filter(|m| !m.is_empty())
+        .map_or(Some(0), |m| m.parse().ok())?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Returns how long before now the time is, e.g. `12s ago`, or after it, e.g. `in 5m`.
+pub fn relative(time: DateTime<FixedOffset>, now: DateTime<FixedOffset>) -> String {
+    let seconds = (now - time).num_seconds();
+    let (amount, unit) = match seconds.unsigned_abs() {
+        s if s < 60 => (s, "s"),
+        s if s < 60 * 60 => (s / 60, "m"),
+        s if s < 24 * 60 * 60 => (s / (60 * 60), "h"),
+        s => (s / (24 * 60 * 60), "d"),
+    };
+    if seconds < 0 {
+        format!("in {}{}", amount, unit)
+    } else {
+        format!("{}{} ago", amount, unit)
+    }
+}
+
+/// Where a term of a time filter puts its bound.
+enum Bound {
+    At(DateTime<FixedOffset>),
+    /// The time of day of any date, e.g. `10:32:00`.
+    TimeOfDay(NaiveTime),
+}
+
+/// A filter of lines by their timestamps, e.g. `last 5m` or `time>=10:32:00, time<11:00`.
+pub struct Range {
+    /// The filter as entered.
+    pub text: String,
+    /// Bounds all of which the timestamps must be on the right side of.
+    terms: Vec<(Op, Bound)>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Range {
+    /// Parses terms separated by commas, each either `last <amount><unit>`
+    /// (with s, m, h or d as the unit) or a comparison such as `time>=10:32:00`,
+    /// `>2024-04-12 10:00` or `time<2024-04-12T10:00:00Z`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = vec![];
+        for term in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if let Some(duration) = term.strip_prefix("last") {
+                let since = clock::now()
+                    .checked_sub_signed(parse_duration(duration)?)
+                    .ok_or_else(|| format!("duration out of range: {}", duration.trim()))?;
+                terms.push((Op::Ge, Bound::At(since)));
+                continue;
+            }
+
+            let rest = term.strip_prefix("time").unwrap_or(term).trim_start();
+            let (op, value) = [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt)]
+                .into_iter()
+                .find_map(|(token, op)| rest.strip_prefix(token).map(|value| (op, value)))
+                .ok_or_else(|| format!("expected `last 5m` or `time>=10:32:00`: {}", term))?;
+            terms.push((op, parse_bound(value.trim())?));
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            terms,
+        })
+    }
+
+    /// Returns whether the time is within all the bounds,
+    /// times of day being of the local time whatever the offset of the time.
+    pub fn admits(&self, time: DateTime<FixedOffset>) -> bool {
+        let local = time
+            .with_timezone(&clock::local_offset(time.timestamp()))
+            .time();
+        self.terms.iter().all(|(op, bound)| {
+            let ordering = match bound {
+                Bound::At(at) => time.cmp(at),
+                Bound::TimeOfDay(at) => local.with_nanosecond(0).unwrap_or(local).cmp(at),
+            };
+            match op {
+                Op::Gt => ordering.is_gt(),
+                Op::Ge => ordering.is_ge(),
+                Op::Lt => ordering.is_lt(),
+                Op::Le => ordering.is_le(),
+            }
+        })
+    }
+}
+
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let amount: i64 = text[..split]
+        .parse()
+        .map_err(|_| format!("expected a duration such as 5m: {}", text))?;
+    let seconds = match text[split..].trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        unit => return Err(format!("expected s, m, h or d as the unit: {}", unit)),
+    };
+    amount
+        .checked_mul(seconds)
+        .and_then(Duration::try_seconds)
+        .ok_or_else(|| format!("duration out of range: {}", text))
+}
+
+fn parse_bound(text: &str) -> Result<Bound, String> {
+    for format in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(text, format) {
+            return Ok(Bound::TimeOfDay(time));
+        }
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(Bound::At(time));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(Bound::At(clock::localize(time)));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(Bound::At(clock::localize(date.and_time(NaiveTime::MIN))));
+    }
+    Err(format!(
+        "expected a time such as 10:32:00 or 2024-04-12 10:32:00: {}",
+        text
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    fn local(text: &str) -> DateTime<FixedOffset> {
+        clock::localize(NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap())
+    }
+
+    #[test]
+    fn parses_known_formats() {
+        let parser = Parser::default();
+        assert_eq!(
+            parser.parse("ts=2024-04-12T10:32:00.5Z msg=x"),
+            Some(at("2024-04-12T10:32:00.5Z"))
+        );
+        assert_eq!(
+            parser.parse("2024-04-12 10:32:00,123 +09:00 started"),
+            Some(at("2024-04-12T10:32:00.123+09:00"))
+        );
+        assert_eq!(
+            parser.parse(r#"1.2.3.4 - - [12/Apr/2024:10:32:00 -0500] "GET /""#),
+            Some(at("2024-04-12T10:32:00-05:00"))
+        );
+        // Without an offset, in local time.
+        assert_eq!(
+            parser.parse("2024-04-12 10:32:00 started"),
+            Some(local("2024-04-12 10:32:00"))
+        );
+        let year = clock::now().year();
+        assert_eq!(
+            parser.parse("E0412 10:32:00.123456 1 main.go:10] failed"),
+            Some(local(&format!("{}-04-12 10:32:00", year)) + Duration::microseconds(123456))
+        );
+        assert_eq!(
+            parser.parse("Apr  2 10:32:00 host sshd[1]: accepted"),
+            Some(local(&format!("{}-04-02 10:32:00", year)))
+        );
+        assert_eq!(parser.parse("no time here, only 10:32"), None);
+    }
+
+    #[test]
+    fn tries_the_given_format_first() {
+        let parser = Parser {
+            format: Some(String::from("%Y/%m/%d %H:%M:%S")),
+        };
+        assert_eq!(
+            parser.parse("[2024/04/12 10:32:00] started"),
+            Some(local("2024-04-12 10:32:00"))
+        );
+        // Falling back to the known formats.
+        assert_eq!(
+            parser.parse("2024-04-12T10:32:00Z"),
+            Some(at("2024-04-12T10:32:00Z"))
+        );
+    }
+
+    #[test]
+    fn filters_by_bounds() {
+        let range = Range::parse("time>2024-04-12T10:00:00+09:00, <=2024-04-12T11:00:00Z").unwrap();
+        assert!(range.admits(at("2024-04-12T01:00:01Z")));
+        assert!(!range.admits(at("2024-04-12T01:00:00Z")));
+        assert!(range.admits(at("2024-04-12T11:00:00Z")));
+        assert!(!range.admits(at("2024-04-12T12:00:00+01:00") + Duration::seconds(1)));
+
+        // Without an offset, in local time.
+        let range = Range::parse("time>=2024-04-12 10:00").unwrap();
+        assert!(range.admits(local("2024-04-12 10:00:00")));
+        assert!(!range.admits(local("2024-04-12 09:59:59")));
+        assert!(Range::parse("time<2024-04-12")
+            .unwrap()
+            .admits(local("2024-04-11 23:59:59")));
+    }
+
+    #[test]
+    fn compares_times_of_day_in_local_time() {
+        let range = Range::parse("time>=10:32, time<11:00").unwrap();
+        let time = local("2024-04-12 10:40:00");
+        assert!(range.admits(time));
+        // The same instant written at another offset.
+        assert!(range.admits(time.with_timezone(&FixedOffset::east_opt(9 * 3600).unwrap())));
+        assert!(!range.admits(local("2024-04-12 11:00:00")));
+        assert!(!range.admits(local("2024-04-13 10:31:59")));
+    }
+
+    #[test]
+    fn filters_by_durations() {
+        let range = Range::parse("last 5m").unwrap();
+        assert!(range.admits(clock::now() - Duration::minutes(4)));
+        assert!(!range.admits(clock::now() - Duration::minutes(6)));
+        assert!(Range::parse("last 2 hours").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_and_out_of_range_filters() {
+        for text in [
+            "last",
+            "last 5",
+            "last 5y",
+            "last m",
+            "last 99999999999999999999m",
+            "last 9223372036854775807d",
+            "last 9999999999999d",
+            "time=10:32",
+            "time>noon",
+        ] {
+            assert!(Range::parse(text).is_err(), "{}", text);
+        }
+        assert!(Range::parse("").unwrap().admits(clock::now()));
+    }
+
+    #[test]
+    fn describes_relative_times() {
+        let now = at("2024-04-12T10:32:00Z");
+        assert_eq!(relative(now - Duration::seconds(12), now), "12s ago");
+        assert_eq!(relative(now - Duration::minutes(90), now), "1h ago");
+        assert_eq!(relative(now + Duration::minutes(5), now), "in 5m");
+        assert_eq!(relative(now - Duration::days(3), now), "3d ago");
+    }
+}
@@ -0,0 +1,117 @@
+use promkit::text_editor;
+
+use crate::editing;
+
+/// Shown before the prompt while in normal mode.
+const NORMAL: &str = "(normal) ";
+
+/// Commands of normal mode, as listed in the help.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("i, a", "Switch to insert mode before or after the cursor"),
+    (
+        "I, A",
+        "Switch to insert mode at the start or end of the query",
+    ),
+    ("h, l", "Move the cursor one character to the left or right"),
+    ("w, b", "Move the cursor to the next or previous word"),
+    ("0, $", "Move the cursor to the start or end of the query"),
+    ("x", "Delete the character at the cursor"),
+    ("dw, db", "Delete to the next or previous word"),
+    ("dd", "Delete the whole query"),
+    ("u", "Undo the last change to the query"),
+    ("?", "Show the key bindings and settings"),
+];
+
+/// Vi-style modal editing of the query.
+#[derive(Default)]
+pub struct Vi {
+    normal: bool,
+    /// Operator waiting for its motion, e.g. `d` of `dw`.
+    operator: Option<char>,
+}
+
+impl Vi {
+    /// Returns the keymap to go back to when a nested prompt (e.g. history search) ends.
+    pub fn keymap(&self) -> &'static str {
+        if self.normal {
+            "normal"
+        } else {
+            "default"
+        }
+    }
+
+    pub fn is_normal(&self) -> bool {
+        self.normal
+    }
+
+    pub fn enter_normal(&mut self, state: &mut text_editor::State) {
+        if !self.normal {
+            self.normal = true;
+            state.prefix = format!("{}{}", NORMAL, state.prefix);
+            // As in vi, the cursor rests on the last character rather than after it.
+            state.texteditor.backward();
+        }
+    }
+
+    pub fn enter_insert(&mut self, state: &mut text_editor::State) {
+        self.normal = false;
+        self.operator = None;
+        if let Some(prefix) = state.prefix.strip_prefix(NORMAL) {
+            state.prefix = prefix.to_string();
+        }
+    }
+
+    /// Forgets the pending operator, if any.
+    pub fn cancel(&mut self) {
+        self.operator = None;
+    }
+
+    /// Applies a key typed in normal mode, returning false if it is not a command.
+    pub fn command(&mut self, state: &mut text_editor::State, ch: char) -> bool {
+        let editor = &mut state.texteditor;
+        let words = &state.word_break_chars;
+        match (self.operator.take(), ch) {
+            (Some('d'), 'w') => editor.erase_to_next_nearest(words),
+            (Some('d'), 'b') => editor.erase_to_previous_nearest(words),
+            (Some('d'), 'd') => editor.erase_all(),
+            // An unknown motion cancels the operator, as in vi.
+            (Some(_), _) => (),
+
+            (None, 'd') => self.operator = Some(ch),
+            (None, 'x') => editing::delete(state),
+
+            (None, 'h') => {
+                editor.backward();
+            }
+            (None, 'l') => {
+                if editor.forward() && editor.position() == editor.text_without_cursor().len() {
+                    editor.backward();
+                }
+            }
+            (None, 'w') => editor.move_to_next_nearest(words),
+            (None, 'b') => editor.move_to_previous_nearest(words),
+            (None, '0') => editor.move_to_head(),
+            (None, '$') => {
+                editor.move_to_tail();
+                editor.backward();
+            }
+
+            (None, 'i') => self.enter_insert(state),
+            (None, 'a') => {
+                editor.forward();
+                self.enter_insert(state);
+            }
+            (None, 'I') => {
+                editor.move_to_head();
+                self.enter_insert(state);
+            }
+            (None, 'A') => {
+                editor.move_to_tail();
+                self.enter_insert(state);
+            }
+
+            _ => return false,
+        }
+        true
+    }
+}